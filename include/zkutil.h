@@ -0,0 +1,86 @@
+#ifndef ZKUTIL_H
+#define ZKUTIL_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit manually */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define ZKUTIL_OK 0
+
+#define ZKUTIL_ERR_NULL_POINTER 1
+
+#define ZKUTIL_ERR_INVALID_DATA 2
+
+#define ZKUTIL_ERR_PROVING 3
+
+#define ZKUTIL_ERR_VERIFYING 4
+
+#define ZKUTIL_ERR_PANIC 5
+
+/**
+ * Loaded proving parameters, with the verifying key prepared once for all verifications
+ */
+typedef struct ZkutilParams ZkutilParams;
+
+/**
+ * Loaded circuit constraints
+ */
+typedef struct ZkutilR1cs ZkutilR1cs;
+
+/**
+ * Loaded witness
+ */
+typedef struct ZkutilWitness ZkutilWitness;
+
+/**
+ * Returns description of the error of the last call on the current thread or NULL
+ * if it succeeded, the string is owned by the library and valid until the next call
+ */
+const char *zkutil_last_error(void);
+
+/**
+ * Loads params.bin contents
+ */
+int zkutil_params_load(const uint8_t *data, size_t len, ZkutilParams **out);
+
+void zkutil_params_free(ZkutilParams *params);
+
+/**
+ * Loads circuit.r1cs or circuit.json contents
+ */
+int zkutil_r1cs_load(const uint8_t *data, size_t len, ZkutilR1cs **out);
+
+void zkutil_r1cs_free(ZkutilR1cs *r1cs);
+
+/**
+ * Loads witness.wtns or witness.json contents
+ */
+int zkutil_witness_load(const uint8_t *data, size_t len, ZkutilWitness **out);
+
+void zkutil_witness_free(ZkutilWitness *witness);
+
+/**
+ * Generates a proof, returns proof and public inputs JSON strings
+ * that should be released with `zkutil_string_free`
+ */
+int zkutil_prove(const ZkutilParams *params,
+                 const ZkutilR1cs *r1cs,
+                 const ZkutilWitness *witness,
+                 char **proof_json,
+                 char **public_json);
+
+/**
+ * Verifies a proof, sets `valid` to 1 if the proof is correct and to 0 otherwise
+ */
+int zkutil_verify(const ZkutilParams *params,
+                  const char *proof_json,
+                  const char *public_json,
+                  int *valid);
+
+void zkutil_string_free(char *s);
+
+#endif  /* ZKUTIL_H */
@@ -0,0 +1,12 @@
+// Builds the addon and copies the library to zkutil.node, its file name depends on the platform
+const { execFileSync } = require('node:child_process');
+const fs = require('node:fs');
+const path = require('node:path');
+
+const LIBRARY = {
+    darwin: 'libzkutil.dylib',
+    win32: 'zkutil.dll',
+}[process.platform] || 'libzkutil.so';
+
+execFileSync('cargo', ['build', '--release', '--lib', '--features', 'node'], { cwd: __dirname, stdio: 'inherit' });
+fs.copyFileSync(path.join(__dirname, '..', 'target', 'release', LIBRARY), path.join(__dirname, 'zkutil.node'));
@@ -0,0 +1,34 @@
+/** Trusted setup parameters */
+export class Params {}
+
+/** Circuit constraints */
+export class R1CS {
+  /** Parses circuit JSON in circom format */
+  static fromJson(data: string): R1CS
+  get numInputs(): number
+  get numVariables(): number
+  get numConstraints(): number
+}
+
+/** Groth16 proof along with its public inputs */
+export class Proof {
+  /** Parses proof JSON in snarkjs format, public inputs are given separately */
+  static fromJson(data: string, publicSignals: Array<string>): Proof
+  get publicSignals(): Array<string>
+}
+
+/** Loads params.bin */
+export function loadParams(path: string): Promise<Params>
+/** Generates random parameters for the circuit, not suitable for production use */
+export function setup(circuit: R1CS): Promise<Params>
+/** Loads circuit from circom .r1cs or .json file */
+export function loadR1cs(path: string): Promise<R1CS>
+/** Parses .wtns file contents into a list of decimal strings */
+export function witnessFromBin(data: Buffer): Promise<Array<string>>
+/** Generates a proof for the witness given as a list of decimal strings */
+export function prove(params: Params, circuit: R1CS, witness: Array<string>): Promise<Proof>
+export function verify(params: Params, proof: Proof, publicSignals: Array<string>): Promise<boolean>
+/** Proof in snarkjs compatible JSON format */
+export function proofToJson(proof: Proof): string
+/** Solidity verifier contract source */
+export function createVerifierSol(params: Params): string
@@ -0,0 +1,70 @@
+const test = require('node:test');
+const assert = require('node:assert');
+const fs = require('node:fs');
+const os = require('node:os');
+const path = require('node:path');
+const zkutil = require('../zkutil.node');
+
+// x * x = y
+const CIRCUIT = { constraints: [[{ 2: '1' }, { 2: '1' }, { 1: '1' }]], nPubInputs: 0, nOutputs: 1, nVars: 3 };
+
+const PRIME = 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001n;
+
+function wtns(values) {
+    const header = Buffer.alloc(4 + 32 + 4);
+    header.writeUInt32LE(32, 0);
+    for (let i = 0; i < 4; i++) {
+        header.writeBigUInt64LE((PRIME >> BigInt(64 * i)) & 0xffffffffffffffffn, 4 + 8 * i);
+    }
+    header.writeUInt32LE(values.length, 36);
+    const witness = Buffer.alloc(32 * values.length);
+    values.forEach((v, i) => witness.writeUInt32LE(v, 32 * i));
+    const section = (type, data) => {
+        const head = Buffer.alloc(12);
+        head.writeUInt32LE(type, 0);
+        head.writeBigUInt64LE(BigInt(data.length), 4);
+        return Buffer.concat([head, data]);
+    };
+    const preamble = Buffer.alloc(12);
+    preamble.write('wtns', 0);
+    preamble.writeUInt32LE(2, 4);
+    preamble.writeUInt32LE(2, 8);
+    return Buffer.concat([preamble, section(1, header), section(2, witness)]);
+}
+
+async function loadCircuit() {
+    const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'zkutil-'));
+    fs.writeFileSync(path.join(dir, 'circuit.json'), JSON.stringify(CIRCUIT));
+    return zkutil.loadR1cs(path.join(dir, 'circuit.json'));
+}
+
+test('prove and verify', async () => {
+    const circuit = await loadCircuit();
+    assert.strictEqual(circuit.numConstraints, 1);
+    const params = await zkutil.setup(circuit);
+    const witness = await zkutil.witnessFromBin(wtns([1, 9, 3]));
+    assert.deepStrictEqual(witness, ['1', '9', '3']);
+
+    const proof = await zkutil.prove(params, circuit, witness);
+    assert.deepStrictEqual(proof.publicSignals, ['9']);
+    assert.strictEqual(await zkutil.verify(params, proof, ['9']), true);
+    assert.strictEqual(await zkutil.verify(params, proof, ['16']), false);
+    const parsed = zkutil.Proof.fromJson(zkutil.proofToJson(proof), ['9']);
+    assert.strictEqual(await zkutil.verify(params, parsed, parsed.publicSignals), true);
+    assert.match(zkutil.createVerifierSol(params), /contract Verifier/);
+    assert.strictEqual(zkutil.R1CS.fromJson(JSON.stringify(CIRCUIT)).numVariables, 3);
+});
+
+test('errors', async () => {
+    const circuit = await loadCircuit();
+    const params = await zkutil.setup(circuit);
+    await assert.rejects(zkutil.loadParams('missing.bin'));
+    const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'zkutil-'));
+    fs.writeFileSync(path.join(dir, 'circuit.json'), '{}');
+    await assert.rejects(zkutil.loadR1cs(path.join(dir, 'circuit.json')), /missing field `constraints`/);
+    assert.throws(() => zkutil.R1CS.fromJson('{"constraints": [], "nPubInputs": 2, "nOutputs": 1, "nVars": 3}'), /fewer than 4 inputs/);
+    await assert.rejects(zkutil.witnessFromBin(Buffer.from('not a witness')));
+    await assert.rejects(zkutil.prove(params, circuit, ['1']), /Witness has 1 values, circuit has 3 wires/);
+    await assert.rejects(zkutil.prove(params, circuit, ['1', '9', '3', '4']), /Witness has 4 values/);
+    assert.throws(() => zkutil.prove(params, circuit, ['1', 'x', '3']), /Invalid field element/);
+});
@@ -0,0 +1,54 @@
+import json
+
+import pytest
+import zkutil
+
+CIRCUIT = {"constraints": [[{"2": "1"}, {"2": "1"}, {"1": "1"}]], "nPubInputs": 0, "nOutputs": 1, "nVars": 3}
+
+
+@pytest.fixture
+def circuit(tmp_path):
+    path = tmp_path / "circuit.json"
+    path.write_text(json.dumps(CIRCUIT))
+    return zkutil.R1CS.from_file(str(path))
+
+
+def test_prove_and_verify(circuit, tmp_path):
+    assert circuit.num_constraints == 1
+    params = zkutil.setup(circuit)
+    params.save(str(tmp_path / "params.bin"))
+    params = zkutil.load_params(str(tmp_path / "params.bin"))
+
+    proof, public = params.prove(circuit, [1, 9, 3])
+    assert public == ["9"]
+    assert zkutil.verify(params.vk, proof, public)
+    assert zkutil.verify(params.vk, proof.to_json(), [9])
+    assert not zkutil.verify(params.vk, proof, ["16"])
+    assert len(proof.to_calldata()) == 8 * 64
+    assert "IC" in json.loads(zkutil.verification_key_to_json(params))
+    assert "contract" in zkutil.verifier_contract(params)
+
+    witness = tmp_path / "witness.json"
+    witness.write_text(json.dumps(["1", "16", "4"]))
+    proof, public = zkutil.prove(params, circuit, str(witness))
+    assert public == ["16"]
+    assert zkutil.verify(params.vk, proof, public)
+
+
+def test_errors(circuit, tmp_path):
+    with pytest.raises(FileNotFoundError):
+        zkutil.load_params(str(tmp_path / "missing.bin"))
+    with pytest.raises(FileNotFoundError):
+        zkutil.R1CS.from_file(str(tmp_path / "missing.json"))
+    (tmp_path / "bad.json").write_text("{}")
+    with pytest.raises(ValueError):
+        zkutil.R1CS.from_file(str(tmp_path / "bad.json"))
+    with pytest.raises(ValueError):
+        zkutil.load_witness(str(tmp_path / "bad.json"))
+    params = zkutil.setup(circuit)
+    with pytest.raises(ValueError):
+        params.prove(circuit, ["1"])
+    with pytest.raises(ValueError):
+        params.prove(circuit, ["1", "x", "3"])
+    with pytest.raises(zkutil.ZkutilError):
+        zkutil.verify(params.vk, params.prove(circuit, [1, 9, 3])[0], [])
@@ -4,12 +4,14 @@ extern crate rand;
 
 use std::str;
 use std::fs::{self, OpenOptions, File};
-use std::io::{BufReader, Read, Seek};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, Write};
 use std::collections::BTreeMap;
 use std::iter::repeat;
 use std::sync::Arc;
 use itertools::Itertools;
 use rand::{Rng, OsRng};
+use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian};
+use sha2::{Digest, Sha256};
 
 use bellman_ce::{
     Circuit,
@@ -22,16 +24,16 @@ use bellman_ce::{
     groth16::{
         Parameters,
         Proof,
+        VerifyingKey,
         generate_random_parameters as generate_random_parameters2,
-        prepare_verifying_key,
-        create_random_proof,
-        verify_proof,
         prepare_prover,
     },
     pairing::{
         Engine,
         CurveAffine,
+        ff::Field,
         ff::PrimeField,
+        ff::PrimeFieldRepr,
         ff::ScalarEngine,
         bn256::{
             Bn256,
@@ -44,12 +46,15 @@ use bellman_ce::{
 };
 
 use crate::utils::{
+    bytes_to_hex,
     repr_to_big,
     proof_to_hex,
     p1_to_vec,
     p2_to_vec,
     pairing_to_vec,
 };
+use crate::prover::Prover;
+use crate::verifier::Verifier;
 
 #[derive(Serialize, Deserialize)]
 struct CircuitJson {
@@ -71,6 +76,12 @@ struct ProofJson {
     pub pi_c: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+struct ProofBatchEntryJson {
+    pub proof: ProofJson,
+    pub public: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize)]
 struct ProvingKeyJson {
     #[serde(rename = "polsA")]
@@ -128,6 +139,60 @@ pub type Constraint<E> = (
     Vec<(usize, <E as ScalarEngine>::Fr)>,
 );
 
+const PARAMS_MAGIC: [u8; 4] = [0x7a, 0x6b, 0x70, 0x6d]; // magic = "zkpm"
+const PARAMS_VERSION: u32 = 2;
+const PARAMS_FLAG_FILTERED: u32 = 1;
+const PARAMS_FLAG_CIRCUIT_HASH: u32 = 2;
+
+/// zkutil header that precedes bellman parameters in params.bin
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParamsHeader {
+    /// Points at infinity are already dropped, the prover can use params as is
+    pub filtered: bool,
+    /// `R1CS::hash` of the circuit params were generated for
+    pub circuit_hash: Option<[u8; 32]>,
+}
+
+impl ParamsHeader {
+    fn read<R: Read>(mut reader: R) -> io::Result<ParamsHeader> {
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version == 0 || version > PARAMS_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported params version"));
+        }
+        let flags = reader.read_u32::<LittleEndian>()?;
+        let mut circuit_hash = None;
+        // version 1 files have no circuit hash
+        if version >= 2 && flags & PARAMS_FLAG_CIRCUIT_HASH != 0 {
+            let mut hash = [0u8; 32];
+            reader.read_exact(&mut hash)?;
+            circuit_hash = Some(hash);
+        }
+        Ok(ParamsHeader {
+            filtered: flags & PARAMS_FLAG_FILTERED != 0,
+            circuit_hash,
+        })
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(PARAMS_VERSION)?;
+        let mut flags = 0;
+        if self.filtered {
+            flags |= PARAMS_FLAG_FILTERED;
+        }
+        if self.circuit_hash.is_some() {
+            flags |= PARAMS_FLAG_CIRCUIT_HASH;
+        }
+        writer.write_u32::<LittleEndian>(flags)?;
+        if let Some(hash) = &self.circuit_hash {
+            writer.write_all(hash)?;
+        }
+        Ok(())
+    }
+}
+
+/// A proof together with the public inputs it should be verified against
+pub type ProofWithInputs<E> = (Proof<E>, Vec<<E as ScalarEngine>::Fr>);
+
 #[derive(Clone)]
 pub struct R1CS<E: Engine> {
     pub num_inputs: usize,
@@ -136,6 +201,80 @@ pub struct R1CS<E: Engine> {
     pub constraints: Vec<Constraint<E>>,
 }
 
+impl<E: Engine> R1CS<E> {
+    /// Canonical SHA-256 hash of the constraint system that doesn't depend on
+    /// the file format or the order of terms within linear combinations
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = circuit_hasher(self.num_inputs, self.num_aux, self.constraints.len());
+        for constraint in self.constraints.iter() {
+            hash_constraint::<E>(&mut hasher, constraint);
+        }
+        hasher.finalize().into()
+    }
+
+    /// Index of the first constraint that the witness doesn't satisfy
+    pub fn first_unsatisfied(&self, witness: &[E::Fr]) -> Option<usize> {
+        self.constraints.iter().position(|c| !is_satisfied::<E>(c, witness))
+    }
+}
+
+/// Value of a linear combination for the witness
+pub fn eval_lc<E: Engine>(lc: &[(usize, E::Fr)], witness: &[E::Fr]) -> E::Fr {
+    let mut result = E::Fr::zero();
+    for (index, coeff) in lc.iter() {
+        let mut term = witness[*index];
+        term.mul_assign(coeff);
+        result.add_assign(&term);
+    }
+    result
+}
+
+pub fn is_satisfied<E: Engine>(constraint: &Constraint<E>, witness: &[E::Fr]) -> bool {
+    let mut ab = eval_lc::<E>(&constraint.0, witness);
+    ab.mul_assign(&eval_lc::<E>(&constraint.1, witness));
+    ab == eval_lc::<E>(&constraint.2, witness)
+}
+
+/// Hasher for `R1CS::hash` after the circuit size, constraints are added with `hash_constraint`
+pub(crate) fn circuit_hasher(num_inputs: usize, num_aux: usize, num_constraints: usize) -> Sha256 {
+    let mut hasher = Sha256::new();
+    hasher.update((num_inputs as u64).to_le_bytes());
+    hasher.update((num_aux as u64).to_le_bytes());
+    hasher.update((num_constraints as u64).to_le_bytes());
+    hasher
+}
+
+pub(crate) fn hash_constraint<E: Engine>(hasher: &mut Sha256, constraint: &Constraint<E>) {
+    for lc in [&constraint.0, &constraint.1, &constraint.2].iter() {
+        let terms = lc.iter().sorted_by_key(|(index, _)| *index).collect_vec();
+        hasher.update((terms.len() as u64).to_le_bytes());
+        for (index, coeff) in terms {
+            hasher.update((*index as u64).to_le_bytes());
+            coeff.into_repr().write_le(&mut *hasher).unwrap();
+        }
+    }
+}
+
+/// Checks that params were generated for the circuit, params without a circuit hash are accepted
+pub fn check_circuit_hash<E: Engine>(header: &ParamsHeader, r1cs: &R1CS<E>) -> io::Result<()> {
+    match header.circuit_hash {
+        Some(_) => check_hash(header, r1cs.hash()),
+        None => Ok(()),
+    }
+}
+
+/// Same as `check_circuit_hash` for an already computed circuit hash
+pub fn check_hash(header: &ParamsHeader, hash: [u8; 32]) -> io::Result<()> {
+    match header.circuit_hash {
+        Some(expected) if expected != hash => Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Params were generated for a different circuit: expected circuit hash {}, got {}",
+            bytes_to_hex(&expected),
+            bytes_to_hex(&hash),
+        ))),
+        _ => Ok(()),
+    }
+}
+
 #[derive(Clone)]
 pub struct CircomCircuit<E: Engine> {
     pub r1cs: R1CS<E>,
@@ -145,94 +284,148 @@ pub struct CircomCircuit<E: Engine> {
 }
 
 impl<'a, E: Engine> CircomCircuit<E> {
+    /// Borrows the circuit, so that it can be synthesized without consuming it
+    pub fn by_ref(&self) -> CircomCircuitRef<'_, E> {
+        CircomCircuitRef {
+            r1cs: &self.r1cs,
+            witness: self.witness.as_deref(),
+            wire_mapping: self.wire_mapping.as_deref(),
+        }
+    }
+
+    pub fn get_public_inputs(&self) -> Option<Vec<E::Fr>> {
+        self.by_ref().get_public_inputs()
+    }
+
+    pub fn get_public_inputs_json(&self) -> String {
+        self.by_ref().get_public_inputs_json()
+    }
+}
+
+/// Borrowed version of `CircomCircuit`, allows proving many witnesses
+/// for the same R1CS without copying it.
+pub struct CircomCircuitRef<'a, E: Engine> {
+    pub r1cs: &'a R1CS<E>,
+    pub witness: Option<&'a [E::Fr]>,
+    pub wire_mapping: Option<&'a [usize]>,
+}
+
+impl<'a, E: Engine> Clone for CircomCircuitRef<'a, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E: Engine> Copy for CircomCircuitRef<'a, E> {}
+
+impl<'a, E: Engine> CircomCircuitRef<'a, E> {
+    /// None without witness or if the witness doesn't have a value for every wire
     pub fn get_public_inputs(&self) -> Option<Vec<E::Fr>> {
-        match &self.witness {
+        let num_variables = self.r1cs.num_variables;
+        match self.witness {
             None => None,
-            Some(w) => match &self.wire_mapping {
-                None => Some(w[1..self.r1cs.num_inputs].to_vec()),
-                Some(m) => Some(m[1..self.r1cs.num_inputs].iter().map(|i| w[*i]).collect_vec()),
+            Some(w) => match self.wire_mapping {
+                None if w.len() == num_variables => Some(w[1..self.r1cs.num_inputs].to_vec()),
+                Some(m) if m.len() == num_variables && m.iter().all(|i| *i < w.len()) =>
+                    Some(m[1..self.r1cs.num_inputs].iter().map(|i| w[*i]).collect_vec()),
+                _ => None,
             }
         }
     }
 
     pub fn get_public_inputs_json(&self) -> String {
-        let inputs = self.get_public_inputs();
-        let inputs = match inputs {
-            None => return String::from("[]"),
-            Some(inp) => inp.iter().map(|x| repr_to_big(x.into_repr())).collect_vec(),
-        };
-        serde_json::to_string_pretty(&inputs).unwrap()
+        match self.get_public_inputs() {
+            None => String::from("[]"),
+            Some(inputs) => public_inputs_json::<E>(&inputs),
+        }
+    }
+}
+
+/// Public inputs in public.json format
+pub fn public_inputs_json<E: Engine>(inputs: &[E::Fr]) -> String {
+    let inputs = inputs.iter().map(|x| repr_to_big(x.into_repr())).collect_vec();
+    serde_json::to_string_pretty(&inputs).unwrap()
+}
+
+impl<'a, E: Engine> Circuit<E> for CircomCircuit<E> {
+    fn synthesize<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS
+    ) -> Result<(), SynthesisError>
+    {
+        self.by_ref().synthesize(cs)
     }
 }
 
 /// Our demo circuit implements this `Circuit` trait which
 /// is used during paramgen and proving in order to
 /// synthesize the constraint system.
-impl<'a, E: Engine> Circuit<E> for CircomCircuit<E> {
+impl<'a, E: Engine> Circuit<E> for CircomCircuitRef<'a, E> {
     //noinspection RsBorrowChecker
     fn synthesize<CS: ConstraintSystem<E>>(
         self,
         cs: &mut CS
     ) -> Result<(), SynthesisError>
     {
-        let witness = &self.witness;
-        let wire_mapping = &self.wire_mapping;
-        for i in 1..self.r1cs.num_inputs {
-            cs.alloc_input(
-                || format!("variable {}", i),
-                || {
-                    Ok(match witness {
-                        None => E::Fr::from_str("1").unwrap(),
-                        Some(w) => match wire_mapping {
-                            None => w[i],
-                            Some(m) => w[m[i]],
-                        }
-                    })
-                },
-            )?;
-        }
-
-        for i in 0..self.r1cs.num_aux {
-            cs.alloc(
-                || format!("aux {}", i),
-                || {
-                    Ok(match witness {
-                        None => E::Fr::from_str("1").unwrap(),
-                        Some(w) => match wire_mapping {
-                            None => w[i + self.r1cs.num_inputs],
-                            Some(m) => w[m[i + self.r1cs.num_inputs]],
-                        },
-                    })
-                },
-            )?;
-        }
-
-        let make_index = |index|
-            if index < self.r1cs.num_inputs {
-                Index::Input(index)
-            } else {
-                Index::Aux(index - self.r1cs.num_inputs)
-            };
-        let make_lc = |lc_data: Vec<(usize, E::Fr)>|
-            lc_data.iter().fold(
-                LinearCombination::<E>::zero(),
-                |lc: LinearCombination<E>, (index, coeff)| lc + (*coeff, Variable::new_unchecked(make_index(*index)))
-            );
+        alloc_wires(cs, self.r1cs.num_inputs, self.r1cs.num_aux, self.witness, self.wire_mapping)?;
         for (i, constraint) in self.r1cs.constraints.iter().enumerate() {
-            cs.enforce(|| format!("constraint {}", i),
-                       |_| make_lc(constraint.0.clone()),
-                       |_| make_lc(constraint.1.clone()),
-                       |_| make_lc(constraint.2.clone()),
-            );
+            enforce_constraint(cs, self.r1cs.num_inputs, i, constraint);
         }
         Ok(())
     }
 }
 
-pub fn prove<E: Engine, R: Rng>(circuit: CircomCircuit<E>, params: &Parameters<E>, mut rng: R) -> Result<Proof<E>, SynthesisError> {
-    let mut params2 = params.clone();
-    filter_params(&mut params2);
-    create_random_proof(circuit, &params2, &mut rng)
+/// Allocates public and private wires, values are taken from the witness if there is one
+pub(crate) fn alloc_wires<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    num_inputs: usize,
+    num_aux: usize,
+    witness: Option<&[E::Fr]>,
+    wire_mapping: Option<&[usize]>,
+) -> Result<(), SynthesisError> {
+    let value = |i: usize| Ok(match witness {
+        None => E::Fr::from_str("1").unwrap(),
+        Some(w) => match wire_mapping {
+            None => w[i],
+            Some(m) => w[m[i]],
+        },
+    });
+    for i in 1..num_inputs {
+        cs.alloc_input(|| format!("variable {}", i), || value(i))?;
+    }
+    for i in 0..num_aux {
+        cs.alloc(|| format!("aux {}", i), || value(i + num_inputs))?;
+    }
+    Ok(())
+}
+
+/// Variable of a wire allocated by `alloc_wires`
+pub(crate) fn wire_variable(num_inputs: usize, index: usize) -> Variable {
+    if index < num_inputs {
+        Variable::new_unchecked(Index::Input(index))
+    } else {
+        Variable::new_unchecked(Index::Aux(index - num_inputs))
+    }
+}
+
+/// Enforces the constraint on wires allocated by `alloc_wires`
+pub(crate) fn enforce_constraint<E: Engine, CS: ConstraintSystem<E>>(cs: &mut CS, num_inputs: usize, i: usize, constraint: &Constraint<E>) {
+    let make_lc = |lc_data: &[(usize, E::Fr)]|
+        lc_data.iter().fold(
+            LinearCombination::<E>::zero(),
+            |lc: LinearCombination<E>, (index, coeff)| lc + (*coeff, wire_variable(num_inputs, *index))
+        );
+    cs.enforce(|| format!("constraint {}", i),
+               |_| make_lc(&constraint.0),
+               |_| make_lc(&constraint.1),
+               |_| make_lc(&constraint.2),
+    );
+}
+
+/// Filters a copy of the params on every call, keep a `Prover` around to prove more than once
+#[deprecated(note = "use `Prover::prove`, which filters the params only once")]
+pub fn prove<E: Engine, R: Rng>(circuit: CircomCircuit<E>, params: &Parameters<E>, rng: R) -> Result<Proof<E>, SynthesisError> {
+    Prover::new(params.clone()).prove(circuit, rng)
 }
 
 pub fn generate_random_parameters<E: Engine, R: Rng>(circuit: CircomCircuit<E>, mut rng: R) -> Result<Parameters<E>, SynthesisError> {
@@ -244,11 +437,21 @@ pub fn verify_circuit<E: Engine>(circuit: &CircomCircuit<E>, params: &Parameters
         None => return Err(SynthesisError::AssignmentMissing),
         Some(inp) => inp,
     };
-    verify_proof(&prepare_verifying_key(&params.vk), proof, &inputs)
+    Verifier::new(&params.vk).verify(proof, &inputs)
 }
 
 pub fn verify<E: Engine>(params: &Parameters<E>, proof: &Proof<E>, inputs: &[E::Fr]) -> Result<bool, SynthesisError> {
-    verify_proof(&prepare_verifying_key(&params.vk), proof, &inputs)
+    Verifier::new(&params.vk).verify(proof, inputs)
+}
+
+/// Verifies many proofs against the same verifying key at once, see `Verifier::verify_batch`
+pub fn verify_batch<E: Engine>(vk: &VerifyingKey<E>, proofs: &[ProofWithInputs<E>]) -> Result<bool, SynthesisError> {
+    Verifier::new(vk).verify_batch(proofs)
+}
+
+/// Returns indexes of the invalid proofs in a batch, see `Verifier::find_invalid_proofs`
+pub fn find_invalid_proofs<E: Engine>(vk: &VerifyingKey<E>, proofs: &[ProofWithInputs<E>]) -> Result<Vec<usize>, SynthesisError> {
+    Verifier::new(vk).find_invalid_proofs(proofs)
 }
 
 pub fn create_verifier_sol(params: &Parameters<Bn256>) -> String {
@@ -322,7 +525,44 @@ pub fn load_params_file(filename: &str) -> Parameters<Bn256> {
 }
 
 pub fn load_params<R: Read>(reader: R) -> Parameters<Bn256> {
-    Parameters::read(reader, true).expect("unable to read params")
+    load_params_with_header(reader).expect("unable to read params").1
+}
+
+pub fn load_params_file_with_header(filename: &str) -> io::Result<(ParamsHeader, Parameters<Bn256>)> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    load_params_with_header(BufReader::new(reader))
+}
+
+/// Loads params along with zkutil header. Legacy files without the header are
+/// still accepted, in that case a default header is returned.
+pub fn load_params_with_header<R: Read>(mut reader: R) -> io::Result<(ParamsHeader, Parameters<Bn256>)> {
+    let (header, prefix) = read_params_header(&mut reader)?;
+    Ok((header, Parameters::read((&prefix[..]).chain(reader), true)?))
+}
+
+/// Reads zkutil params header. Legacy files get a default header, along with
+/// the consumed bytes that have to be read again as the start of params.
+pub(crate) fn read_params_header<R: Read>(mut reader: R) -> io::Result<(ParamsHeader, Vec<u8>)> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != PARAMS_MAGIC {
+        // legacy files start with a big endian field element that can't begin with "z"
+        return Ok((ParamsHeader::default(), magic.to_vec()));
+    }
+    Ok((ParamsHeader::read(&mut reader)?, vec![]))
+}
+
+pub fn write_params<W: Write>(params: &Parameters<Bn256>, header: &ParamsHeader, mut writer: W) -> io::Result<()> {
+    writer.write_all(&PARAMS_MAGIC)?;
+    header.write(&mut writer)?;
+    params.write(writer)
+}
+
+pub fn write_params_file(params: &Parameters<Bn256>, header: &ParamsHeader, filename: &str) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(filename)?);
+    write_params(params, header, writer)
 }
 
 pub fn load_inputs_json_file<E: Engine>(filename: &str) -> Vec<E::Fr> {
@@ -348,64 +588,144 @@ pub fn load_proof_json_file<E: Engine>(filename: &str) -> Proof<Bn256> {
 
 pub fn load_proof_json<R: Read>(reader: R) -> Proof<Bn256> {
     let proof: ProofJson = serde_json::from_reader(reader).unwrap();
-    Proof {
-        a: G1Affine::from_xy_checked(
-            Fq::from_str(&proof.pi_a[0]).unwrap(),
-            Fq::from_str(&proof.pi_a[1]).unwrap(),
-        ).unwrap(),
-        b: G2Affine::from_xy_checked(
-            Fq2 {
-                c0: Fq::from_str(&proof.pi_b[0][0]).unwrap(),
-                c1: Fq::from_str(&proof.pi_b[0][1]).unwrap(),
-            },
-            Fq2 {
-                c0: Fq::from_str(&proof.pi_b[1][0]).unwrap(),
-                c1: Fq::from_str(&proof.pi_b[1][1]).unwrap(),
-            },
-        ).unwrap(),
-        c: G1Affine::from_xy_checked(
-            Fq::from_str(&proof.pi_c[0]).unwrap(),
-            Fq::from_str(&proof.pi_c[1]).unwrap(),
-        ).unwrap(),
+    proof_from_json(&proof).unwrap()
+}
+
+/// Loads a batch of proofs from JSONL, one `{"proof": {...}, "public": [...]}` object per line.
+/// Every proof comes with its line number, malformed lines keep their own error.
+pub fn load_proof_batch_jsonl_file(filename: &str) -> io::Result<Vec<(usize, io::Result<ProofWithInputs<Bn256>>)>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    load_proof_batch_jsonl(BufReader::new(reader))
+}
+
+pub fn load_proof_batch_jsonl<R: BufRead>(reader: R) -> io::Result<Vec<(usize, io::Result<ProofWithInputs<Bn256>>)>> {
+    let mut proofs = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            proofs.push((i + 1, parse_proof_with_inputs_json(&line)));
+        }
     }
+    Ok(proofs)
+}
+
+/// Parses proof JSON, returns an error instead of panicking on malformed data
+pub fn parse_proof_json(data: &str) -> io::Result<Proof<Bn256>> {
+    let proof: ProofJson = serde_json::from_str(data)?;
+    proof_from_json(&proof)
+}
+
+/// Parses public inputs JSON, returns an error instead of panicking on malformed data
+pub fn parse_inputs_json<E: Engine>(data: &str) -> io::Result<Vec<E::Fr>> {
+    let inputs: Vec<String> = serde_json::from_str(data)?;
+    inputs.iter().map(|x| parse_field::<E::Fr>(x)).collect()
+}
+
+/// Parses a `{"proof": {...}, "public": [...]}` object, returns an error instead of panicking on malformed data
+pub fn parse_proof_with_inputs_json(data: &str) -> io::Result<ProofWithInputs<Bn256>> {
+    let entry: ProofBatchEntryJson = serde_json::from_str(data)?;
+    let inputs = entry.public.iter()
+        .map(|x| parse_field::<<Bn256 as ScalarEngine>::Fr>(x))
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok((proof_from_json(&entry.proof)?, inputs))
+}
+
+fn parse_field<F: PrimeField>(value: &str) -> io::Result<F> {
+    F::from_str(value).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid field element {}", value)))
+}
+
+fn proof_from_json(proof: &ProofJson) -> io::Result<Proof<Bn256>> {
+    let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let coord = |v: &[String], i: usize| -> io::Result<Fq> {
+        parse_field(v.get(i).ok_or_else(|| invalid("Proof point is too short"))?)
+    };
+    let coord2 = |v: &[Vec<String>], i: usize| -> io::Result<Fq2> {
+        let c = v.get(i).ok_or_else(|| invalid("Proof point is too short"))?;
+        Ok(Fq2 { c0: coord(c, 0)?, c1: coord(c, 1)? })
+    };
+    Ok(Proof {
+        a: G1Affine::from_xy_checked(coord(&proof.pi_a, 0)?, coord(&proof.pi_a, 1)?)
+            .map_err(|e| invalid(&e.to_string()))?,
+        b: G2Affine::from_xy_checked(coord2(&proof.pi_b, 0)?, coord2(&proof.pi_b, 1)?)
+            .map_err(|e| invalid(&e.to_string()))?,
+        c: G1Affine::from_xy_checked(coord(&proof.pi_c, 0)?, coord(&proof.pi_c, 1)?)
+            .map_err(|e| invalid(&e.to_string()))?,
+    })
 }
 
 pub fn filter_params<E: Engine>(params: &mut Parameters<E>) {
-    params.vk.ic = params.vk.ic.clone().into_iter().filter(|x| !x.is_zero()).collect::<Vec<_>>();
-    params.h = Arc::new((*params.h).clone().into_iter().filter(|x| !x.is_zero()).collect::<Vec<_>>());
-    params.a = Arc::new((*params.a).clone().into_iter().filter(|x| !x.is_zero()).collect::<Vec<_>>());
-    params.b_g1 = Arc::new((*params.b_g1).clone().into_iter().filter(|x| !x.is_zero()).collect::<Vec<_>>());
-    params.b_g2 = Arc::new((*params.b_g2).clone().into_iter().filter(|x| !x.is_zero()).collect::<Vec<_>>());
+    params.vk.ic.retain(|x| !x.is_zero());
+    filter_points(&mut params.h);
+    filter_points(&mut params.a);
+    filter_points(&mut params.b_g1);
+    filter_points(&mut params.b_g2);
 }
 
-pub fn proving_key_json(params: &Parameters<Bn256>, circuit: CircomCircuit<Bn256>) -> Result<String, serde_json::error::Error> {
-    let mut pols_a: Vec<BTreeMap<String, String>> = vec![];
-    let mut pols_b: Vec<BTreeMap<String, String>> = vec![];
-    let mut pols_c: Vec<BTreeMap<String, String>> = vec![];
-    for _ in 0..circuit.r1cs.num_aux + circuit.r1cs.num_inputs {
-        pols_a.push(BTreeMap::new());
-        pols_b.push(BTreeMap::new());
-        pols_c.push(BTreeMap::new());
+/// Drops points at infinity, the vector is only copied if it's shared and has something to drop
+fn filter_points<G: CurveAffine>(points: &mut Arc<Vec<G>>) {
+    if points.iter().any(|x| x.is_zero()) {
+        Arc::make_mut(points).retain(|x| !x.is_zero());
     }
-    for c in 0..circuit.r1cs.constraints.len() {
-        for item in circuit.r1cs.constraints[c].0.iter() {
-            pols_a[item.0].insert(c.to_string(), repr_to_big(item.1.into_repr()));
-        }
-        for item in circuit.r1cs.constraints[c].1.iter() {
-            pols_b[item.0].insert(c.to_string(), repr_to_big(item.1.into_repr()));
+}
+
+/// A, B and C polynomial coefficients by wire for proving key export
+pub(crate) struct KeyPols {
+    a: Vec<BTreeMap<String, String>>,
+    b: Vec<BTreeMap<String, String>>,
+    c: Vec<BTreeMap<String, String>>,
+}
+
+impl KeyPols {
+    pub(crate) fn new(num_variables: usize) -> KeyPols {
+        KeyPols {
+            a: vec![BTreeMap::new(); num_variables],
+            b: vec![BTreeMap::new(); num_variables],
+            c: vec![BTreeMap::new(); num_variables],
         }
-        for item in circuit.r1cs.constraints[c].2.iter() {
-            pols_c[item.0].insert(c.to_string(), repr_to_big(item.1.into_repr()));
+    }
+
+    /// Adds a term of the constraint, `matrix` is 0, 1 or 2 for A, B or C
+    pub(crate) fn insert<F: PrimeField>(&mut self, matrix: usize, constraint: usize, wire: usize, coeff: &F) {
+        let pols = match matrix {
+            0 => &mut self.a,
+            1 => &mut self.b,
+            _ => &mut self.c,
+        };
+        pols[wire].insert(constraint.to_string(), repr_to_big(coeff.into_repr()));
+    }
+}
+
+pub fn proving_key_json(params: &Parameters<Bn256>, circuit: CircomCircuit<Bn256>) -> Result<String, serde_json::error::Error> {
+    let mut pols = KeyPols::new(circuit.r1cs.num_aux + circuit.r1cs.num_inputs);
+    for (c, constraint) in circuit.r1cs.constraints.iter().enumerate() {
+        for (matrix, lc) in [&constraint.0, &constraint.1, &constraint.2].iter().enumerate() {
+            for item in lc.iter() {
+                pols.insert(matrix, c, item.0, &item.1);
+            }
         }
     }
+    let (num_inputs, num_constraints) = (circuit.r1cs.num_inputs, circuit.r1cs.constraints.len());
+    proving_key_json_with_pols(params, circuit, pols, num_inputs, num_constraints)
+}
 
-    for i in 0..circuit.r1cs.num_inputs {
-        pols_a[i].insert((circuit.r1cs.constraints.len() + i).to_string(), String::from("1"));
+/// Builds proving key JSON from polynomial coefficients and any circuit with the same constraints
+pub(crate) fn proving_key_json_with_pols<C: Circuit<Bn256>>(
+    params: &Parameters<Bn256>,
+    circuit: C,
+    pols: KeyPols,
+    num_inputs: usize,
+    num_constraints: usize,
+) -> Result<String, serde_json::error::Error> {
+    let KeyPols { a: mut pols_a, b: pols_b, c: pols_c } = pols;
+    for i in 0..num_inputs {
+        pols_a[i].insert((num_constraints + i).to_string(), String::from("1"));
     }
 
-    let domain_bits = log2_floor(circuit.r1cs.constraints.len() + circuit.r1cs.num_inputs) + 1;
-    let n_public = circuit.r1cs.num_inputs - 1;
-    let n_vars = circuit.r1cs.num_variables;
+    let domain_bits = log2_floor(num_constraints + num_inputs) + 1;
+    let n_public = num_inputs - 1;
+    let n_vars = pols_a.len();
 
     let p = prepare_prover(circuit).unwrap().assignment;
     let mut a_iter = params.a.iter();
@@ -456,6 +776,11 @@ pub fn proving_key_json(params: &Parameters<Bn256>, circuit: CircomCircuit<Bn256
     serde_json::to_string(&proving_key)
 }
 
+/// Number of bits in the evaluation domain size used for proving key export
+pub fn domain_bits<E: Engine>(r1cs: &R1CS<E>) -> usize {
+    log2_floor(r1cs.constraints.len() + r1cs.num_inputs) + 1
+}
+
 fn log2_floor(num: usize) -> usize {
     assert!(num > 0);
     let mut pow = 0;
@@ -500,16 +825,57 @@ pub fn witness_from_json_file<E: Engine>(filename: &str) -> Vec<E::Fr> {
     witness_from_json::<E, BufReader<File>>(BufReader::new(reader))
 }
 
+/// Same as `witness_from_json_file`, returns an error instead of panicking on a missing file or malformed data
+pub fn try_witness_from_json_file<E: Engine>(filename: &str) -> io::Result<Vec<E::Fr>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    try_witness_from_json::<E, BufReader<File>>(BufReader::new(reader))
+}
+
+/// Field elements parsed while reading the JSON array, without keeping all decimal strings in memory
+struct FieldElements<F>(Vec<F>);
+
+impl<'de, F: PrimeField> serde::Deserialize<'de> for FieldElements<F> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<F>(std::marker::PhantomData<F>);
+
+        impl<'de, F: PrimeField> serde::de::Visitor<'de> for Visitor<F> {
+            type Value = FieldElements<F>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an array of decimal strings")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(value) = seq.next_element::<std::borrow::Cow<str>>()? {
+                    let fr = F::from_str(&value)
+                        .ok_or_else(|| serde::de::Error::custom(format!("Invalid field element {}", value)))?;
+                    result.push(fr);
+                }
+                Ok(FieldElements(result))
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor(std::marker::PhantomData))
+    }
+}
+
 pub fn witness_from_json<E: Engine, R: Read>(reader: R) -> Vec<E::Fr> {
-    let witness: Vec<String> = serde_json::from_reader(reader).unwrap();
-    witness.into_iter().map(|x| E::Fr::from_str(&x).unwrap()).collect::<Vec<E::Fr>>()
+    try_witness_from_json::<E, R>(reader).unwrap()
+}
+
+/// Same as `witness_from_json`, returns an error instead of panicking on malformed data
+pub fn try_witness_from_json<E: Engine, R: Read>(reader: R) -> io::Result<Vec<E::Fr>> {
+    let witness: FieldElements<E::Fr> = serde_json::from_reader(reader)?;
+    Ok(witness.0)
 }
 
 pub fn witness_from_bin_file<E: Engine>(filename: &str) -> Result<Vec<E::Fr>, std::io::Error> {
     let reader = OpenOptions::new()
         .read(true)
-        .open(filename)
-        .expect("unable to open.");
+        .open(filename)?;
     witness_from_bin::<E, BufReader<File>>(BufReader::new(reader))
 }
 
@@ -526,26 +892,47 @@ pub fn r1cs_from_json_file<E: Engine>(filename: &str) -> R1CS<E> {
     r1cs_from_json(BufReader::new(reader))
 }
 
+/// Same as `r1cs_from_json_file`, returns an error instead of panicking on a missing file or malformed data
+pub fn try_r1cs_from_json_file<E: Engine>(filename: &str) -> io::Result<R1CS<E>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    try_r1cs_from_json(BufReader::new(reader))
+}
+
 pub fn r1cs_from_json<E: Engine, R: Read>(reader: R) -> R1CS<E> {
-    let circuit_json: CircuitJson = serde_json::from_reader(reader).unwrap();
+    try_r1cs_from_json(reader).unwrap()
+}
+
+/// Same as `r1cs_from_json`, returns an error instead of panicking on malformed data
+pub fn try_r1cs_from_json<E: Engine, R: Read>(reader: R) -> io::Result<R1CS<E>> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let circuit_json: CircuitJson = serde_json::from_reader(reader)?;
 
     let num_inputs = circuit_json.num_inputs + circuit_json.num_outputs + 1;
-    let num_aux = circuit_json.num_variables - num_inputs;
+    let num_aux = circuit_json.num_variables.checked_sub(num_inputs)
+        .ok_or_else(|| invalid(format!("Circuit has {} variables, fewer than {} inputs", circuit_json.num_variables, num_inputs)))?;
 
     let convert_constraint = |lc: &BTreeMap<String, String>| {
-        lc.iter().map(|(index, coeff)| (index.parse().unwrap(), E::Fr::from_str(coeff).unwrap())).collect_vec()
+        lc.iter().map(|(index, coeff)| {
+            let index = index.parse::<usize>().ok()
+                .filter(|index| *index < circuit_json.num_variables)
+                .ok_or_else(|| invalid(format!("Invalid wire index {}", index)))?;
+            Ok((index, parse_field::<E::Fr>(coeff)?))
+        }).collect::<io::Result<Vec<_>>>()
     };
 
-    let constraints = circuit_json.constraints.iter().map(
-        |c| (convert_constraint(&c[0]), convert_constraint(&c[1]), convert_constraint(&c[2]))
-    ).collect_vec();
+    let constraints = circuit_json.constraints.iter().map(|c| match &c[..] {
+        [a, b, c] => Ok((convert_constraint(a)?, convert_constraint(b)?, convert_constraint(c)?)),
+        _ => Err(invalid(format!("Constraint has {} linear combinations, expected 3", c.len()))),
+    }).collect::<io::Result<Vec<_>>>()?;
 
-    R1CS {
+    Ok(R1CS {
         num_inputs,
         num_aux,
         num_variables: circuit_json.num_variables,
         constraints,
-    }
+    })
 }
 
 pub fn r1cs_from_bin<R: Read + Seek>(reader: R) -> Result<(R1CS<Bn256>, Vec<usize>), std::io::Error> {
@@ -562,11 +949,103 @@ pub fn r1cs_from_bin<R: Read + Seek>(reader: R) -> Result<(R1CS<Bn256>, Vec<usiz
 pub fn r1cs_from_bin_file(filename: &str) -> Result<(R1CS<Bn256>, Vec<usize>), std::io::Error> {
     let reader = OpenOptions::new()
         .read(true)
-        .open(filename)
-        .expect("unable to open.");
+        .open(filename)?;
     r1cs_from_bin(BufReader::new(reader))
 }
 
 pub fn create_rng() -> Box<dyn Rng> {
     Box::new(OsRng::new().unwrap())
 }
+
+#[cfg(test)]
+pub(crate) fn sample_circuit(x: u64) -> CircomCircuit<Bn256> {
+    // x * x = y, y is public
+    let fr = |v: u64| <Bn256 as ScalarEngine>::Fr::from_str(&v.to_string()).unwrap();
+    let one = fr(1);
+    CircomCircuit {
+        r1cs: R1CS {
+            num_inputs: 2,
+            num_aux: 1,
+            num_variables: 3,
+            constraints: vec![(vec![(2, one)], vec![(2, one)], vec![(1, one)])],
+        },
+        witness: Some(vec![one, fr(x * x), fr(x)]),
+        wire_mapping: None,
+    }
+}
+
+#[test]
+fn batch_verification() {
+    let mut fixture = crate::fixture::fixture(0);
+    let prover = fixture.prover();
+    let mut proofs = (1..5).map(|x| {
+        let circuit = sample_circuit(x);
+        let inputs = circuit.get_public_inputs().unwrap();
+        (prover.prove(circuit, &mut fixture.rng).unwrap(), inputs)
+    }).collect_vec();
+    assert!(verify_batch(&fixture.params.vk, &proofs).unwrap());
+    assert!(find_invalid_proofs(&fixture.params.vk, &proofs).unwrap().is_empty());
+
+    proofs[2].1 = sample_circuit(7).get_public_inputs().unwrap();
+    assert!(!verify_batch(&fixture.params.vk, &proofs).unwrap());
+    assert_eq!(find_invalid_proofs(&fixture.params.vk, &proofs).unwrap(), vec![2]);
+}
+
+#[test]
+fn proof_batch_jsonl() {
+    let mut fixture = crate::fixture::fixture(3);
+    let inputs = fixture.circuit.get_public_inputs().unwrap();
+    let proof = fixture.prove_and_verify(fixture.circuit.clone(), &inputs);
+    let proof_json = proof_to_json(&proof).unwrap();
+    let jsonl = format!(
+        "{{\"proof\": {0}, \"public\": [\"9\"]}}\n\nnot json\n{{\"proof\": {0}, \"public\": [\"x\"]}}\n",
+        proof_json.replace('\n', ""),
+    );
+    let entries = load_proof_batch_jsonl(jsonl.as_bytes()).unwrap();
+    // blank lines are skipped but don't shift line numbers
+    assert_eq!(entries.iter().map(|(line, _)| *line).collect_vec(), vec![1, 3, 4]);
+    assert!(entries[0].1.as_ref().unwrap() == &(proof, inputs));
+    assert!(entries[1].1.is_err());
+    assert_eq!(entries[2].1.as_ref().err().unwrap().to_string(), "Invalid field element x");
+}
+
+#[test]
+fn params_header() {
+    let params = crate::fixture::fixture(0).params;
+
+    let mut legacy = vec![];
+    params.write(&mut legacy).unwrap();
+    let (header, loaded) = load_params_with_header(&legacy[..]).unwrap();
+    assert_eq!(header, ParamsHeader::default());
+    assert!(loaded == params);
+
+    let mut data = vec![];
+    let circuit_hash = Some(sample_circuit(0).r1cs.hash());
+    write_params(&params, &ParamsHeader { filtered: true, circuit_hash }, &mut data).unwrap();
+    let (header, loaded) = load_params_with_header(&data[..]).unwrap();
+    assert!(header.filtered);
+    assert!(loaded == params);
+    check_circuit_hash(&header, &sample_circuit(3).r1cs).unwrap();
+
+    let mut other = sample_circuit(0).r1cs;
+    other.constraints[0].2[0].0 = 2;
+    assert!(check_circuit_hash(&header, &other).is_err());
+}
+
+#[test]
+fn public_inputs_witness_length() {
+    use bellman_ce::pairing::bn256::Fr;
+    let circuit = sample_circuit(3);
+    let witness = circuit.witness.clone().unwrap();
+    let with = |witness: &[Fr], wire_mapping: Option<&[usize]>| {
+        CircomCircuitRef { r1cs: &circuit.r1cs, witness: Some(witness), wire_mapping }.get_public_inputs()
+    };
+    assert_eq!(with(&witness, None), Some(witness[1..2].to_vec()));
+    assert_eq!(with(&witness[..2], None), None);
+    assert_eq!(with(&[witness.clone(), witness.clone()].concat(), None), None);
+    // a wire map picks the values from a witness of the original circuit
+    let original = [witness[0], witness[2], witness[2], witness[1]];
+    assert_eq!(with(&original, Some(&[0, 3, 1])), Some(witness[1..2].to_vec()));
+    assert_eq!(with(&original, Some(&[0, 4, 1])), None);
+    assert_eq!(with(&original, Some(&[0, 3])), None);
+}
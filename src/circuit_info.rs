@@ -0,0 +1,177 @@
+extern crate bellman_ce;
+
+use std::fs::OpenOptions;
+use std::io::{self, BufReader};
+use bellman_ce::pairing::bn256::Bn256;
+
+use crate::circom_circuit::{R1CS, domain_bits, try_r1cs_from_json_file};
+use crate::r1cs_reader::{self, R1CSFile};
+
+// sizes of uncompressed bn256 points as written to params.bin
+const G1_SIZE: u64 = 64;
+const G2_SIZE: u64 = 128;
+const FR_SIZE: u64 = 32;
+
+/// Circuit size summary with rough estimates of setup and proving costs
+#[derive(Serialize, Debug)]
+pub struct CircuitInfo {
+    pub constraints: usize,
+    pub wires: usize,
+    /// Public outputs, public inputs and private inputs are only known for .r1cs files
+    pub public_outputs: Option<usize>,
+    pub public_inputs: Option<usize>,
+    pub private_inputs: Option<usize>,
+    /// Number of all public signals, outputs and inputs
+    pub public: usize,
+    pub private: usize,
+    pub labels: Option<u64>,
+    pub nonzeros_a: usize,
+    pub nonzeros_b: usize,
+    pub nonzeros_c: usize,
+    /// Evaluation domain of the snarkjs proving key export, twice the prover's one
+    /// when constraints plus public inputs is a power of two
+    pub domain_bits: usize,
+    pub domain_size: usize,
+    /// Estimated size of params.bin in bytes
+    pub params_size: u64,
+    /// Estimated peak prover memory in bytes
+    pub prover_memory: u64,
+}
+
+impl CircuitInfo {
+    pub fn new(r1cs: &R1CS<Bn256>, header: Option<&r1cs_reader::Header>) -> CircuitInfo {
+        let mut in_a = vec![false; r1cs.num_variables];
+        let mut in_b = vec![false; r1cs.num_variables];
+        let mut used = vec![false; r1cs.num_variables];
+        for (a, b, c) in r1cs.constraints.iter() {
+            for (i, _) in a.iter() {
+                in_a[*i] = true;
+                used[*i] = true;
+            }
+            for (i, _) in b.iter() {
+                in_b[*i] = true;
+                used[*i] = true;
+            }
+            for (i, _) in c.iter() {
+                used[*i] = true;
+            }
+        }
+        let count = |flags: &[bool]| flags.iter().filter(|x| **x).count() as u64;
+        let domain_bits = domain_bits(r1cs);
+        let domain_size = 1usize << domain_bits;
+        // bellman evaluates the polynomials on the smallest domain that fits them
+        let prover_domain_size = (r1cs.constraints.len() + r1cs.num_inputs).next_power_of_two() as u64;
+
+        // params keep points only for the wires that affect them, every public
+        // input is added to A by bellman as an extra constraint
+        let num_inputs = r1cs.num_inputs as u64;
+        let h = prover_domain_size - 1;
+        let l = count(&used[r1cs.num_inputs..]);
+        let a = num_inputs + count(&in_a[r1cs.num_inputs..]);
+        let b = count(&in_b);
+        let vk_size = 3 * G1_SIZE + 3 * G2_SIZE + 4 + num_inputs * G1_SIZE;
+        // zkutil header with circuit hash precedes bellman params
+        let params_size = 12 + 32 + vk_size + 5 * 4 + (h + l + a + b) * G1_SIZE + b * G2_SIZE;
+
+        // params, witness and density trackers plus a, b and c polynomial evaluations
+        let prover_memory = params_size + 2 * r1cs.num_variables as u64 * FR_SIZE + 3 * prover_domain_size * FR_SIZE;
+
+        CircuitInfo {
+            constraints: r1cs.constraints.len(),
+            wires: r1cs.num_variables,
+            public_outputs: header.map(|h| h.n_pub_out as usize),
+            public_inputs: header.map(|h| h.n_pub_in as usize),
+            private_inputs: header.map(|h| h.n_prv_in as usize),
+            public: r1cs.num_inputs - 1,
+            private: r1cs.num_aux,
+            labels: header.map(|h| h.n_labels),
+            nonzeros_a: r1cs.constraints.iter().map(|c| c.0.len()).sum(),
+            nonzeros_b: r1cs.constraints.iter().map(|c| c.1.len()).sum(),
+            nonzeros_c: r1cs.constraints.iter().map(|c| c.2.len()).sum(),
+            domain_bits,
+            domain_size,
+            params_size,
+            prover_memory,
+        }
+    }
+}
+
+/// Loads circuit from .r1cs or .json file, header is only available for .r1cs
+pub fn load_r1cs_file_with_header(filename: &str) -> io::Result<(R1CS<Bn256>, Option<r1cs_reader::Header>)> {
+    if filename.ends_with("json") {
+        return Ok((try_r1cs_from_json_file(filename)?, None));
+    }
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    let (r1cs, header) = r1cs_from_file(r1cs_reader::read(BufReader::new(reader))?);
+    Ok((r1cs, Some(header)))
+}
+
+/// Circuit and header of a parsed .r1cs file
+pub fn r1cs_from_file(file: R1CSFile<Bn256>) -> (R1CS<Bn256>, r1cs_reader::Header) {
+    let num_inputs = (1 + file.header.n_pub_in + file.header.n_pub_out) as usize;
+    let num_variables = file.header.n_wires as usize;
+    let r1cs = R1CS {
+        num_inputs,
+        num_aux: num_variables - num_inputs,
+        num_variables,
+        constraints: file.constraints,
+    };
+    (r1cs, file.header)
+}
+
+/// Loads circuit info from .r1cs or .json circuit file
+pub fn load_circuit_info_file(filename: &str) -> io::Result<CircuitInfo> {
+    let (r1cs, header) = load_r1cs_file_with_header(filename)?;
+    Ok(CircuitInfo::new(&r1cs, header.as_ref()))
+}
+
+#[test]
+fn sample_circuit_info() {
+    use crate::circom_circuit::{filter_params, write_params, ParamsHeader};
+
+    let fixture = crate::fixture::fixture(0);
+    let info = CircuitInfo::new(&fixture.circuit.r1cs, None);
+    assert_eq!((info.constraints, info.wires, info.public, info.private), (1, 3, 1, 1));
+    assert_eq!((info.nonzeros_a, info.nonzeros_b, info.nonzeros_c), (1, 1, 1));
+    assert_eq!((info.domain_bits, info.domain_size), (2, 4));
+    assert_eq!(info.public_outputs, None);
+
+    // the estimate is exact for filtered params with a circuit hash
+    let mut params = fixture.params;
+    filter_params(&mut params);
+    let mut data = vec![];
+    write_params(&params, &ParamsHeader { filtered: true, circuit_hash: Some([0; 32]) }, &mut data).unwrap();
+    assert_eq!(info.params_size, data.len() as u64);
+}
+
+#[test]
+fn power_of_two_domain_info() {
+    use crate::circom_circuit::{CircomCircuit, filter_params, generate_random_parameters, write_params, ParamsHeader};
+
+    // 2 constraints and 2 inputs fill the prover's domain of 4 exactly
+    let mut fixture = crate::fixture::fixture(0);
+    let mut r1cs = fixture.circuit.r1cs.clone();
+    r1cs.constraints.push(r1cs.constraints[0].clone());
+    let info = CircuitInfo::new(&r1cs, None);
+    assert_eq!((info.domain_bits, info.domain_size), (3, 8));
+
+    let circuit = CircomCircuit { r1cs, witness: None, wire_mapping: None };
+    let mut params = generate_random_parameters(circuit, &mut fixture.rng).unwrap();
+    assert_eq!(params.h.len(), 3);
+    filter_params(&mut params);
+    let mut data = vec![];
+    write_params(&params, &ParamsHeader { filtered: true, circuit_hash: Some([0; 32]) }, &mut data).unwrap();
+    assert_eq!(info.params_size, data.len() as u64);
+}
+
+#[test]
+fn malformed_circuit_json() {
+    let filename = std::env::temp_dir().join("zkutil_malformed_circuit.json").to_string_lossy().to_string();
+    std::fs::write(&filename, r#"{"constraints": [], "nPubInputs": 1, "nOutputs": 1, "nVars": 2}"#).unwrap();
+    let error = load_circuit_info_file(&filename).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    std::fs::remove_file(&filename).unwrap();
+    assert_eq!(load_circuit_info_file(&filename).err().unwrap().kind(), io::ErrorKind::NotFound);
+}
@@ -0,0 +1,244 @@
+//! Compact R1CS storage as three compressed sparse row matrices. Coefficients
+//! are kept once in a shared table and referenced by index, so that common
+//! values like 1 and -1 take 4 bytes per term instead of a full field element.
+extern crate bellman_ce;
+
+use std::collections::HashMap;
+use sha2::Digest;
+use bellman_ce::{
+    Circuit,
+    ConstraintSystem,
+    LinearCombination,
+    SynthesisError,
+    groth16::Parameters,
+    pairing::{
+        Engine,
+        bn256::Bn256,
+        ff::{Field, PrimeField},
+    },
+};
+
+use crate::circom_circuit::{
+    Constraint,
+    KeyPols,
+    R1CS,
+    alloc_wires,
+    circuit_hasher,
+    hash_constraint,
+    proving_key_json_with_pols,
+    wire_variable,
+};
+
+/// Sparse matrix in compressed sparse row format
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CsrMatrix {
+    /// Row `i` is stored at `row_offsets[i]..row_offsets[i + 1]`
+    pub row_offsets: Vec<usize>,
+    pub columns: Vec<u32>,
+    /// Indices into the shared coefficient table
+    pub coeffs: Vec<u32>,
+}
+
+impl CsrMatrix {
+    fn new(num_rows: usize) -> CsrMatrix {
+        let mut row_offsets = Vec::with_capacity(num_rows + 1);
+        row_offsets.push(0);
+        CsrMatrix { row_offsets, columns: vec![], coeffs: vec![] }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.row_offsets.len() - 1
+    }
+
+    /// Wire and coefficient index of every term in the row
+    pub fn row(&self, i: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let range = self.row_offsets[i]..self.row_offsets[i + 1];
+        self.columns[range.clone()].iter().zip(self.coeffs[range].iter())
+            .map(|(column, coeff)| (*column as usize, *coeff as usize))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrR1CS<E: Engine> {
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub num_variables: usize,
+    /// Distinct coefficients of all matrices
+    pub coefficients: Vec<E::Fr>,
+    pub a: CsrMatrix,
+    pub b: CsrMatrix,
+    pub c: CsrMatrix,
+}
+
+impl<E: Engine> CsrR1CS<E> {
+    pub fn from_r1cs(r1cs: &R1CS<E>) -> CsrR1CS<E> {
+        let num_rows = r1cs.constraints.len();
+        let mut coefficients = vec![];
+        let mut coefficient_ids = HashMap::<Vec<u64>, u32>::new();
+        let mut matrices = [CsrMatrix::new(num_rows), CsrMatrix::new(num_rows), CsrMatrix::new(num_rows)];
+        for constraint in r1cs.constraints.iter() {
+            for (matrix, lc) in matrices.iter_mut().zip([&constraint.0, &constraint.1, &constraint.2].iter()) {
+                for (index, coeff) in lc.iter() {
+                    let id = *coefficient_ids.entry(coeff.into_repr().as_ref().to_vec()).or_insert_with(|| {
+                        coefficients.push(*coeff);
+                        coefficients.len() as u32 - 1
+                    });
+                    matrix.columns.push(*index as u32);
+                    matrix.coeffs.push(id);
+                }
+                matrix.row_offsets.push(matrix.columns.len());
+            }
+        }
+        let [a, b, c] = matrices;
+        CsrR1CS {
+            num_inputs: r1cs.num_inputs,
+            num_aux: r1cs.num_aux,
+            num_variables: r1cs.num_variables,
+            coefficients,
+            a,
+            b,
+            c,
+        }
+    }
+
+    pub fn to_r1cs(&self) -> R1CS<E> {
+        R1CS {
+            num_inputs: self.num_inputs,
+            num_aux: self.num_aux,
+            num_variables: self.num_variables,
+            constraints: (0..self.num_constraints()).map(|i| self.constraint(i)).collect(),
+        }
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.a.num_rows()
+    }
+
+    fn lc(&self, matrix: &CsrMatrix, i: usize) -> Vec<(usize, E::Fr)> {
+        matrix.row(i).map(|(wire, coeff)| (wire, self.coefficients[coeff])).collect()
+    }
+
+    /// Constraint `i` in the `R1CS` representation
+    pub fn constraint(&self, i: usize) -> Constraint<E> {
+        (self.lc(&self.a, i), self.lc(&self.b, i), self.lc(&self.c, i))
+    }
+
+    fn eval_row(&self, matrix: &CsrMatrix, i: usize, witness: &[E::Fr]) -> E::Fr {
+        let mut result = E::Fr::zero();
+        for (wire, coeff) in matrix.row(i) {
+            let mut term = witness[wire];
+            term.mul_assign(&self.coefficients[coeff]);
+            result.add_assign(&term);
+        }
+        result
+    }
+
+    pub fn is_satisfied(&self, i: usize, witness: &[E::Fr]) -> bool {
+        let mut ab = self.eval_row(&self.a, i, witness);
+        ab.mul_assign(&self.eval_row(&self.b, i, witness));
+        ab == self.eval_row(&self.c, i, witness)
+    }
+
+    /// Index of the first constraint that the witness doesn't satisfy
+    pub fn first_unsatisfied(&self, witness: &[E::Fr]) -> Option<usize> {
+        (0..self.num_constraints()).find(|i| !self.is_satisfied(*i, witness))
+    }
+
+    /// Same as `R1CS::hash` of the converted circuit
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = circuit_hasher(self.num_inputs, self.num_aux, self.num_constraints());
+        for i in 0..self.num_constraints() {
+            hash_constraint::<E>(&mut hasher, &self.constraint(i));
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Circuit that synthesizes constraints straight from the CSR matrices
+pub struct CsrCircuit<'a, E: Engine> {
+    pub r1cs: &'a CsrR1CS<E>,
+    pub witness: Option<&'a [E::Fr]>,
+}
+
+impl<'a, E: Engine> CsrCircuit<'a, E> {
+    /// None without witness or if the witness doesn't have a value for every wire
+    pub fn get_public_inputs(&self) -> Option<Vec<E::Fr>> {
+        self.witness
+            .filter(|w| w.len() == self.r1cs.num_variables)
+            .map(|w| w[1..self.r1cs.num_inputs].to_vec())
+    }
+}
+
+impl<'a, E: Engine> Circuit<E> for CsrCircuit<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let r1cs = self.r1cs;
+        alloc_wires(cs, r1cs.num_inputs, r1cs.num_aux, self.witness, None)?;
+        let make_lc = |matrix: &CsrMatrix, i: usize|
+            matrix.row(i).fold(
+                LinearCombination::<E>::zero(),
+                |lc, (wire, coeff)| lc + (r1cs.coefficients[coeff], wire_variable(r1cs.num_inputs, wire))
+            );
+        for i in 0..r1cs.num_constraints() {
+            cs.enforce(|| format!("constraint {}", i),
+                       |_| make_lc(&r1cs.a, i),
+                       |_| make_lc(&r1cs.b, i),
+                       |_| make_lc(&r1cs.c, i),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Same as `circom_circuit::proving_key_json` for a CSR circuit
+pub fn proving_key_json(params: &Parameters<Bn256>, circuit: CsrCircuit<Bn256>) -> Result<String, serde_json::error::Error> {
+    let r1cs = circuit.r1cs;
+    let mut pols = KeyPols::new(r1cs.num_variables);
+    for i in 0..r1cs.num_constraints() {
+        for (m, matrix) in [&r1cs.a, &r1cs.b, &r1cs.c].iter().enumerate() {
+            for (wire, coeff) in matrix.row(i) {
+                pols.insert(m, i, wire, &r1cs.coefficients[coeff]);
+            }
+        }
+    }
+    proving_key_json_with_pols(params, circuit, pols, r1cs.num_inputs, r1cs.num_constraints())
+}
+
+#[test]
+fn csr_roundtrip_and_prove() {
+    use bellman_ce::pairing::{ff::PrimeField, bn256::Fr};
+    use crate::circom_circuit::R1CS;
+
+    let mut fixture = crate::fixture::fixture(3);
+    let circuit = fixture.circuit.clone();
+    let csr = CsrR1CS::from_r1cs(&circuit.r1cs);
+    assert_eq!(csr.to_r1cs().constraints, circuit.r1cs.constraints);
+    assert_eq!(csr.hash(), circuit.r1cs.hash());
+    let mut witness = circuit.witness.clone().unwrap();
+    assert_eq!(csr.first_unsatisfied(&witness), None);
+    witness[1] = witness[2];
+    assert_eq!(csr.first_unsatisfied(&witness), circuit.r1cs.first_unsatisfied(&witness));
+    let witness = circuit.witness.clone().unwrap();
+    assert_eq!(CsrCircuit { r1cs: &csr, witness: Some(&witness[..2]) }.get_public_inputs(), None);
+
+    let csr_circuit = CsrCircuit { r1cs: &csr, witness: Some(&witness) };
+    let inputs = csr_circuit.get_public_inputs().unwrap();
+    fixture.prove_and_verify(csr_circuit, &inputs);
+    let json = proving_key_json(&fixture.params, CsrCircuit { r1cs: &csr, witness: None }).unwrap();
+    assert_eq!(json, crate::circom_circuit::proving_key_json(&fixture.params, circuit).unwrap());
+
+    // repeated coefficients are stored once, empty rows survive the roundtrip
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+    let r1cs = R1CS::<Bn256> {
+        num_inputs: 2,
+        num_aux: 1,
+        num_variables: 3,
+        constraints: vec![
+            (vec![(2, fr("2"))], vec![(2, fr("2")), (0, fr("1"))], vec![(1, fr("1"))]),
+            (vec![], vec![], vec![]),
+        ],
+    };
+    let csr = CsrR1CS::from_r1cs(&r1cs);
+    assert_eq!(csr.coefficients.len(), 2);
+    assert_eq!(csr.to_r1cs().constraints, r1cs.constraints);
+    assert_eq!(csr.hash(), r1cs.hash());
+}
@@ -0,0 +1,297 @@
+//! C ABI for the cdylib build, see `include/zkutil.h`.
+//!
+//! All functions return `ZKUTIL_OK` on success or one of the error codes,
+//! a description of the last error on the current thread is available via
+//! `zkutil_last_error` and is cleared by the next call. Panics never cross the
+//! FFI boundary.
+//! Pointers passed to the library must be either NULL or valid, objects and
+//! strings returned by the library should be released with the corresponding
+//! `zkutil_*_free` function.
+#![allow(clippy::missing_safety_doc)]
+extern crate bellman_ce;
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io::{self, Cursor};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use bellman_ce::pairing::bn256::{Bn256, Fr};
+
+use crate::circom_circuit::{
+    CircomCircuitRef,
+    R1CS,
+    create_rng,
+    parse_inputs_json,
+    parse_proof_json,
+    proof_to_json,
+    r1cs_from_bin,
+    try_r1cs_from_json,
+    witness_from_bin,
+};
+use crate::prover::{Prover, load_prover};
+use crate::utils::synthesis_error_message;
+use crate::verifier::Verifier;
+
+pub const ZKUTIL_OK: c_int = 0;
+pub const ZKUTIL_ERR_NULL_POINTER: c_int = 1;
+pub const ZKUTIL_ERR_INVALID_DATA: c_int = 2;
+pub const ZKUTIL_ERR_PROVING: c_int = 3;
+pub const ZKUTIL_ERR_VERIFYING: c_int = 4;
+pub const ZKUTIL_ERR_PANIC: c_int = 5;
+
+/// Loaded proving parameters, with the verifying key prepared once for all verifications
+pub struct ZkutilParams {
+    prover: Prover<Bn256>,
+    verifier: Verifier<Bn256>,
+}
+
+/// Loaded circuit constraints
+pub struct ZkutilR1cs(R1CS<Bn256>);
+
+/// Loaded witness
+pub struct ZkutilWitness(Vec<Fr>);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+struct FfiError(c_int, String);
+
+impl From<io::Error> for FfiError {
+    fn from(e: io::Error) -> Self {
+        FfiError(ZKUTIL_ERR_INVALID_DATA, e.to_string())
+    }
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Runs `f` converting errors and panics into error codes, the last error is cleared first
+fn guard<F: FnOnce() -> Result<(), FfiError>>(f: F) -> c_int {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => ZKUTIL_OK,
+        Ok(Err(FfiError(code, message))) => {
+            set_last_error(message);
+            code
+        }
+        Err(e) => {
+            let message = e.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| e.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Unknown panic".to_string());
+            set_last_error(message);
+            ZKUTIL_ERR_PANIC
+        }
+    }
+}
+
+unsafe fn buffer<'a>(data: *const u8, len: usize) -> Result<&'a [u8], FfiError> {
+    if data.is_null() {
+        return Err(FfiError(ZKUTIL_ERR_NULL_POINTER, "Buffer is null".to_string()));
+    }
+    Ok(slice::from_raw_parts(data, len))
+}
+
+unsafe fn reference<'a, T>(p: *const T, name: &str) -> Result<&'a T, FfiError> {
+    p.as_ref().ok_or_else(|| FfiError(ZKUTIL_ERR_NULL_POINTER, format!("{} is null", name)))
+}
+
+unsafe fn string<'a>(s: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if s.is_null() {
+        return Err(FfiError(ZKUTIL_ERR_NULL_POINTER, format!("{} is null", name)));
+    }
+    CStr::from_ptr(s).to_str().map_err(|e| FfiError(ZKUTIL_ERR_INVALID_DATA, e.to_string()))
+}
+
+unsafe fn store<T>(out: *mut *mut T, value: T) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError(ZKUTIL_ERR_NULL_POINTER, "Output pointer is null".to_string()));
+    }
+    *out = Box::into_raw(Box::new(value));
+    Ok(())
+}
+
+fn to_c_string(s: String) -> *mut c_char {
+    CString::new(s).unwrap().into_raw()
+}
+
+/// Returns description of the error of the last call on the current thread or NULL
+/// if it succeeded, the string is owned by the library and valid until the next call
+#[no_mangle]
+pub extern "C" fn zkutil_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Loads params.bin contents
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_params_load(data: *const u8, len: usize, out: *mut *mut ZkutilParams) -> c_int {
+    guard(|| {
+        let prover = load_prover(buffer(data, len)?)?;
+        let verifier = prover.verifier();
+        store(out, ZkutilParams { prover, verifier })
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_params_free(params: *mut ZkutilParams) {
+    if !params.is_null() {
+        drop(Box::from_raw(params));
+    }
+}
+
+/// Loads circuit.r1cs or circuit.json contents
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_r1cs_load(data: *const u8, len: usize, out: *mut *mut ZkutilR1cs) -> c_int {
+    guard(|| {
+        let data = buffer(data, len)?;
+        let r1cs = if data.starts_with(b"r1cs") {
+            r1cs_from_bin(Cursor::new(data))?.0
+        } else {
+            try_r1cs_from_json(data)?
+        };
+        store(out, ZkutilR1cs(r1cs))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_r1cs_free(r1cs: *mut ZkutilR1cs) {
+    if !r1cs.is_null() {
+        drop(Box::from_raw(r1cs));
+    }
+}
+
+/// Loads witness.wtns or witness.json contents
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_witness_load(data: *const u8, len: usize, out: *mut *mut ZkutilWitness) -> c_int {
+    guard(|| {
+        let data = buffer(data, len)?;
+        let witness = if data.starts_with(b"wtns") {
+            witness_from_bin::<Bn256, _>(data)?
+        } else {
+            let data = std::str::from_utf8(data).map_err(|e| FfiError(ZKUTIL_ERR_INVALID_DATA, e.to_string()))?;
+            parse_inputs_json::<Bn256>(data)?
+        };
+        store(out, ZkutilWitness(witness))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_witness_free(witness: *mut ZkutilWitness) {
+    if !witness.is_null() {
+        drop(Box::from_raw(witness));
+    }
+}
+
+/// Generates a proof, returns proof and public inputs JSON strings
+/// that should be released with `zkutil_string_free`
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_prove(
+    params: *const ZkutilParams,
+    r1cs: *const ZkutilR1cs,
+    witness: *const ZkutilWitness,
+    proof_json: *mut *mut c_char,
+    public_json: *mut *mut c_char,
+) -> c_int {
+    guard(|| {
+        let params = reference(params, "params")?;
+        let r1cs = reference(r1cs, "r1cs")?;
+        let witness = reference(witness, "witness")?;
+        if proof_json.is_null() || public_json.is_null() {
+            return Err(FfiError(ZKUTIL_ERR_NULL_POINTER, "Output pointer is null".to_string()));
+        }
+        params.prover.check_circuit(&r1cs.0)?;
+        if witness.0.len() != r1cs.0.num_variables {
+            return Err(FfiError(ZKUTIL_ERR_INVALID_DATA, format!("Witness has {} values, circuit has {} wires", witness.0.len(), r1cs.0.num_variables)));
+        }
+        let circuit = CircomCircuitRef {
+            r1cs: &r1cs.0,
+            witness: Some(&witness.0),
+            wire_mapping: None,
+        };
+        let proof = params.prover.prove(circuit, create_rng())
+            .map_err(|e| FfiError(ZKUTIL_ERR_PROVING, synthesis_error_message(&e)))?;
+        let proof = proof_to_json(&proof)
+            .map_err(|e| FfiError(ZKUTIL_ERR_PROVING, e.to_string()))?;
+        *proof_json = to_c_string(proof);
+        *public_json = to_c_string(circuit.get_public_inputs_json());
+        Ok(())
+    })
+}
+
+/// Verifies a proof, sets `valid` to 1 if the proof is correct and to 0 otherwise
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_verify(
+    params: *const ZkutilParams,
+    proof_json: *const c_char,
+    public_json: *const c_char,
+    valid: *mut c_int,
+) -> c_int {
+    guard(|| {
+        let params = reference(params, "params")?;
+        let proof = parse_proof_json(string(proof_json, "proof")?)?;
+        let inputs = parse_inputs_json::<Bn256>(string(public_json, "public")?)?;
+        if valid.is_null() {
+            return Err(FfiError(ZKUTIL_ERR_NULL_POINTER, "Output pointer is null".to_string()));
+        }
+        let correct = params.verifier.verify(&proof, &inputs)
+            .map_err(|e| FfiError(ZKUTIL_ERR_VERIFYING, synthesis_error_message(&e)))?;
+        *valid = correct as c_int;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn zkutil_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[test]
+fn prove_and_verify() {
+    let mut params_data = vec![];
+    crate::fixture::fixture(0).params.write(&mut params_data).unwrap();
+    let r1cs_data = br#"{"constraints": [[{"2": "1"}, {"2": "1"}, {"1": "1"}]], "nPubInputs": 0, "nOutputs": 1, "nVars": 3}"#;
+    let witness_data = br#"["1", "9", "3"]"#;
+
+    unsafe {
+        let mut params = ptr::null_mut();
+        let mut r1cs = ptr::null_mut();
+        let mut witness = ptr::null_mut();
+        assert_eq!(zkutil_params_load(params_data.as_ptr(), params_data.len(), &mut params), ZKUTIL_OK);
+        assert_eq!(zkutil_r1cs_load(r1cs_data.as_ptr(), r1cs_data.len(), &mut r1cs), ZKUTIL_OK);
+        assert_eq!(zkutil_witness_load(witness_data.as_ptr(), witness_data.len(), &mut witness), ZKUTIL_OK);
+
+        let mut proof = ptr::null_mut();
+        let mut public = ptr::null_mut();
+        assert_eq!(zkutil_prove(params, r1cs, witness, &mut proof, &mut public), ZKUTIL_OK);
+        let mut valid = 0;
+        assert_eq!(zkutil_verify(params, proof, public, &mut valid), ZKUTIL_OK);
+        assert_eq!(valid, 1);
+        let no_inputs = b"[]\0";
+        assert_eq!(zkutil_verify(params, proof, no_inputs.as_ptr() as *const c_char, &mut valid), ZKUTIL_ERR_VERIFYING);
+
+        let garbage = b"not a witness";
+        assert_eq!(zkutil_witness_load(garbage.as_ptr(), garbage.len(), &mut witness), ZKUTIL_ERR_INVALID_DATA);
+        assert!(!zkutil_last_error().is_null());
+        let mut other = ptr::null_mut();
+        let bad_r1cs = br#"{"constraints": [[{"2": "1"}, {"7": "1"}, {"1": "1"}]], "nPubInputs": 0, "nOutputs": 1, "nVars": 3}"#;
+        assert_eq!(zkutil_r1cs_load(bad_r1cs.as_ptr(), bad_r1cs.len(), &mut other), ZKUTIL_ERR_INVALID_DATA);
+        assert_eq!(CStr::from_ptr(zkutil_last_error()).to_str().unwrap(), "Invalid wire index 7");
+        let bad_r1cs = br#"{"constraints": [[{"2": "1"}, {"1": "1"}]], "nPubInputs": 0, "nOutputs": 1, "nVars": 3}"#;
+        assert_eq!(zkutil_r1cs_load(bad_r1cs.as_ptr(), bad_r1cs.len(), &mut other), ZKUTIL_ERR_INVALID_DATA);
+        assert_eq!(zkutil_verify(params, proof, public, &mut valid), ZKUTIL_OK);
+        assert!(zkutil_last_error().is_null());
+        assert_eq!(zkutil_prove(params, ptr::null(), witness, &mut proof, &mut public), ZKUTIL_ERR_NULL_POINTER);
+
+        zkutil_string_free(proof);
+        zkutil_string_free(public);
+        zkutil_witness_free(witness);
+        zkutil_r1cs_free(r1cs);
+        zkutil_params_free(params);
+    }
+}
@@ -0,0 +1,38 @@
+//! Shared test setup: `sample_circuit` with parameters generated from a fixed seed
+extern crate bellman_ce;
+
+use rand::{SeedableRng, XorShiftRng};
+use bellman_ce::{
+    Circuit,
+    groth16::{Parameters, Proof},
+    pairing::bn256::{Bn256, Fr},
+};
+
+use crate::circom_circuit::{CircomCircuit, generate_random_parameters, sample_circuit, verify};
+use crate::prover::Prover;
+
+pub(crate) struct Fixture {
+    pub circuit: CircomCircuit<Bn256>,
+    pub params: Parameters<Bn256>,
+    pub rng: XorShiftRng,
+}
+
+pub(crate) fn fixture(x: u64) -> Fixture {
+    let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let circuit = sample_circuit(x);
+    let params = generate_random_parameters(circuit.clone(), &mut rng).unwrap();
+    Fixture { circuit, params, rng }
+}
+
+impl Fixture {
+    pub fn prover(&self) -> Prover<Bn256> {
+        Prover::new(self.params.clone())
+    }
+
+    /// Proves with the fixture params and checks that the proof verifies against `inputs`
+    pub fn prove_and_verify<C: Circuit<Bn256>>(&mut self, circuit: C, inputs: &[Fr]) -> Proof<Bn256> {
+        let proof = self.prover().prove(circuit, &mut self.rng).unwrap();
+        assert!(verify(&self.params, &proof, inputs).unwrap());
+        proof
+    }
+}
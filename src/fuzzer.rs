@@ -0,0 +1,157 @@
+//! Dynamic under-constraint detection: mutates private wires of a valid witness
+//! and tries to repair the rest of it so that every constraint holds again with
+//! the same public inputs. Any repaired witness is an alternative assignment
+//! that a malicious prover could use.
+extern crate bellman_ce;
+extern crate rand;
+
+use std::io;
+use std::collections::VecDeque;
+use rand::Rng;
+use bellman_ce::pairing::{Engine, ff::Field};
+
+use crate::circom_circuit::{R1CS, is_satisfied};
+use crate::solver::solve_for;
+
+/// Alternative witness found by mutating a single private wire
+pub struct FuzzHit<E: Engine> {
+    pub mutated: usize,
+    pub witness: Vec<E::Fr>,
+    /// Wires that differ from the original witness
+    pub diff: Vec<usize>,
+}
+
+pub struct WitnessFuzzer<'a, E: Engine> {
+    r1cs: &'a R1CS<E>,
+    witness: &'a [E::Fr],
+    /// Constraints using every wire
+    users: Vec<Vec<usize>>,
+}
+
+impl<'a, E: Engine> WitnessFuzzer<'a, E> {
+    pub fn new(r1cs: &'a R1CS<E>, witness: &'a [E::Fr]) -> io::Result<Self> {
+        if witness.len() < r1cs.num_variables {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Witness has {} values, circuit has {} wires", witness.len(), r1cs.num_variables)));
+        }
+        if let Some(i) = r1cs.first_unsatisfied(witness) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Witness doesn't satisfy constraint {}", i)));
+        }
+        let mut users = vec![vec![]; r1cs.num_variables];
+        for (i, (a, b, c)) in r1cs.constraints.iter().enumerate() {
+            for (index, _) in a.iter().chain(b.iter()).chain(c.iter()) {
+                if users[*index].last() != Some(&i) {
+                    users[*index].push(i);
+                }
+            }
+        }
+        Ok(WitnessFuzzer { r1cs, witness, users })
+    }
+
+    /// Sets the wire to the value and solves broken constraints for one of their
+    /// other private wires until all of them hold. Returns None if it gets stuck.
+    pub fn mutate(&self, wire: usize, value: E::Fr) -> Option<Vec<E::Fr>> {
+        let mut witness = self.witness[..self.r1cs.num_variables].to_vec();
+        let mut frozen = (0..self.r1cs.num_variables).map(|i| i < self.r1cs.num_inputs).collect::<Vec<_>>();
+        witness[wire] = value;
+        frozen[wire] = true;
+        let mut queue = self.users[wire].iter().cloned().collect::<VecDeque<_>>();
+        while let Some(i) = queue.pop_front() {
+            let constraint = &self.r1cs.constraints[i];
+            if is_satisfied::<E>(constraint, &witness) {
+                continue;
+            }
+            // prefer later wires, circom puts intermediate signals after the inputs
+            let mut candidates = constraint.0.iter().chain(constraint.1.iter()).chain(constraint.2.iter())
+                .map(|(index, _)| *index)
+                .filter(|index| !frozen[*index])
+                .collect::<Vec<_>>();
+            candidates.sort_unstable_by(|a, b| b.cmp(a));
+            candidates.dedup();
+            let (next, value) = candidates.into_iter()
+                .find_map(|index| solve_for::<E>(constraint, &witness, index).map(|value| (index, value)))?;
+            witness[next] = value;
+            frozen[next] = true;
+            queue.extend(self.users[next].iter().cloned());
+        }
+        match self.r1cs.first_unsatisfied(&witness) {
+            None => Some(witness),
+            Some(_) => None,
+        }
+    }
+
+    /// Mutates every private wire with the values most likely to have an alternative
+    /// assignment (negation, 0, 1 and -1), then private wires in turn with random values.
+    /// Reports at most one hit per wire
+    pub fn run<R: Rng>(&self, iterations: usize, rng: &mut R) -> Vec<FuzzHit<E>> {
+        let private = (self.r1cs.num_inputs..self.r1cs.num_variables).collect::<Vec<_>>();
+        let mut hits: Vec<FuzzHit<E>> = vec![];
+        if private.is_empty() {
+            return hits;
+        }
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+        for wire in private.iter().cloned() {
+            let mut negated = self.witness[wire];
+            negated.negate();
+            for value in [negated, E::Fr::zero(), E::Fr::one(), minus_one] {
+                self.try_value(wire, value, &mut hits);
+            }
+        }
+        for i in 0..iterations {
+            self.try_value(private[i % private.len()], rng.gen(), &mut hits);
+        }
+        hits
+    }
+
+    fn try_value(&self, wire: usize, value: E::Fr, hits: &mut Vec<FuzzHit<E>>) {
+        if value == self.witness[wire] || hits.iter().any(|hit| hit.mutated == wire) {
+            return;
+        }
+        if let Some(witness) = self.mutate(wire, value) {
+            let diff = (0..witness.len()).filter(|i| witness[*i] != self.witness[*i]).collect();
+            hits.push(FuzzHit { mutated: wire, witness, diff });
+        }
+    }
+}
+
+#[test]
+fn fuzz_finds_unconstrained() {
+    use bellman_ce::pairing::{ff::PrimeField, bn256::{Bn256, Fr}};
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+
+    // x * x = y has a second root, negation finds it without random values
+    let mut fixture = crate::fixture::fixture(3);
+    let sample = fixture.circuit.witness.as_ref().unwrap();
+    let hits = WitnessFuzzer::new(&fixture.circuit.r1cs, sample).unwrap().run(0, &mut fixture.rng);
+    assert_eq!(hits.iter().map(|hit| (hit.mutated, hit.diff.clone())).collect::<Vec<_>>(), vec![(2, vec![2])]);
+    let mut root = fr("3");
+    root.negate();
+    assert_eq!(hits[0].witness[2], root);
+
+    // wires: 1, out, x, y, z; out = x * x, z = x + y
+    let r1cs = R1CS::<Bn256> {
+        num_inputs: 2,
+        num_aux: 3,
+        num_variables: 5,
+        constraints: vec![
+            (vec![(2, fr("1"))], vec![(2, fr("1"))], vec![(1, fr("1"))]),
+            (vec![(0, fr("1"))], vec![(2, fr("1")), (3, fr("1"))], vec![(4, fr("1"))]),
+        ],
+    };
+    let witness = vec![fr("1"), fr("9"), fr("3"), fr("2"), fr("5")];
+    let fuzzer = WitnessFuzzer::new(&r1cs, &witness).unwrap();
+    assert!(fuzzer.mutate(2, fr("4")).is_none());
+
+    let hits = fuzzer.run(6, &mut fixture.rng);
+    assert_eq!(hits.iter().map(|hit| (hit.mutated, hit.diff.clone())).collect::<Vec<_>>(), vec![
+        (2, vec![2, 4]),
+        (3, vec![3, 4]),
+        (4, vec![3, 4]),
+    ]);
+    for hit in hits.iter() {
+        assert_eq!(r1cs.first_unsatisfied(&hit.witness), None);
+        assert_eq!(hit.witness[1], witness[1]);
+    }
+
+    assert!(WitnessFuzzer::new(&r1cs, &[fr("1"), fr("8"), fr("3"), fr("2"), fr("5")]).is_err());
+}
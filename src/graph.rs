@@ -0,0 +1,236 @@
+//! Wire/constraint bipartite graph of a circuit, exported as DOT or GraphML.
+//! Wire 0 is left out since it's used by almost every constraint.
+extern crate bellman_ce;
+
+use std::io::{self, Write};
+use std::collections::{BTreeMap, HashSet};
+use bellman_ce::pairing::Engine;
+
+use crate::circom_circuit::R1CS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    GraphML,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GraphOptions {
+    /// Merge wires into one node per sym name prefix of this many components, e.g. 2 for `main.hasher`
+    pub collapse: Option<usize>,
+    /// Only keep constraints within the given number of hops from the wire
+    pub around: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Wire,
+    Constraint,
+    /// Wires collapsed by component prefix
+    Group,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+    pub kind: NodeKind,
+    pub public: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    /// Node indices and the number of constraints the edge stands for
+    pub edges: Vec<(usize, usize, usize)>,
+}
+
+fn wire_label(names: &[Option<String>], wire: usize) -> String {
+    names.get(wire).cloned().flatten().unwrap_or_else(|| format!("w{}", wire))
+}
+
+pub fn build_graph<E: Engine>(r1cs: &R1CS<E>, names: &[Option<String>], options: &GraphOptions) -> Graph {
+    let wires = r1cs.constraints.iter()
+        .map(|(a, b, c)| {
+            let mut wires = a.iter().chain(b.iter()).chain(c.iter())
+                .map(|(index, _)| *index)
+                .filter(|index| *index != 0)
+                .collect::<Vec<_>>();
+            wires.sort_unstable();
+            wires.dedup();
+            wires
+        })
+        .collect::<Vec<_>>();
+
+    let mut selected = vec![options.around.is_none(); r1cs.constraints.len()];
+    if let Some((wire, radius)) = options.around {
+        let mut users = vec![vec![]; r1cs.num_variables];
+        for (i, constraint_wires) in wires.iter().enumerate() {
+            for wire in constraint_wires.iter() {
+                users[*wire].push(i);
+            }
+        }
+        let mut visited = HashSet::new();
+        visited.insert(wire);
+        let mut frontier = vec![wire];
+        for _ in 0..radius {
+            let mut next = vec![];
+            for wire in frontier.iter() {
+                for i in users.get(*wire).into_iter().flatten() {
+                    if selected[*i] {
+                        continue;
+                    }
+                    selected[*i] = true;
+                    next.extend(wires[*i].iter().filter(|w| visited.insert(**w)).cloned());
+                }
+            }
+            frontier = next;
+        }
+    }
+
+    let mut graph = Graph::default();
+    let is_public = |wire: usize| wire < r1cs.num_inputs;
+    match options.collapse {
+        None => {
+            let mut wire_nodes = BTreeMap::new();
+            for (i, constraint_wires) in wires.iter().enumerate().filter(|(i, _)| selected[*i]) {
+                let node = graph.nodes.len();
+                graph.nodes.push(Node { id: format!("c{}", i), label: format!("#{}", i), kind: NodeKind::Constraint, public: false });
+                for wire in constraint_wires.iter() {
+                    let wire_node = *wire_nodes.entry(*wire).or_insert_with(|| {
+                        graph.nodes.push(Node { id: format!("w{}", wire), label: wire_label(names, *wire), kind: NodeKind::Wire, public: is_public(*wire) });
+                        graph.nodes.len() - 1
+                    });
+                    graph.edges.push((wire_node, node, 1));
+                }
+            }
+        }
+        Some(depth) => {
+            let group_of = |wire: usize| match names.get(wire).cloned().flatten() {
+                Some(name) => name.split('.').take(depth).collect::<Vec<_>>().join("."),
+                None => format!("w{}", wire),
+            };
+            let mut groups = BTreeMap::new();
+            let mut edges = BTreeMap::new();
+            for constraint_wires in wires.iter().enumerate().filter(|(i, _)| selected[*i]).map(|(_, w)| w) {
+                let mut nodes = vec![];
+                for wire in constraint_wires.iter() {
+                    let group = group_of(*wire);
+                    let node = *groups.entry(group.clone()).or_insert_with(|| {
+                        graph.nodes.push(Node { id: format!("g{}", graph.nodes.len()), label: group, kind: NodeKind::Group, public: false });
+                        graph.nodes.len() - 1
+                    });
+                    graph.nodes[node].public |= is_public(*wire);
+                    nodes.push(node);
+                }
+                nodes.sort_unstable();
+                nodes.dedup();
+                for (i, from) in nodes.iter().enumerate() {
+                    for to in nodes[i + 1..].iter() {
+                        *edges.entry((*from, *to)).or_insert(0) += 1;
+                    }
+                }
+            }
+            graph.edges = edges.into_iter().map(|((from, to), weight)| (from, to, weight)).collect();
+        }
+    }
+    graph
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub fn write_dot<W: Write>(mut writer: W, graph: &Graph) -> io::Result<()> {
+    writeln!(writer, "graph circuit {{")?;
+    for node in graph.nodes.iter() {
+        let mut attrs = vec![format!("label=\"{}\"", escape_dot(&node.label))];
+        if node.kind == NodeKind::Constraint {
+            attrs.push("shape=box".to_string());
+        }
+        if node.public {
+            attrs.push("style=filled".to_string());
+            attrs.push("fillcolor=lightblue".to_string());
+        }
+        writeln!(writer, "  {} [{}];", node.id, attrs.join(", "))?;
+    }
+    for (from, to, weight) in graph.edges.iter() {
+        if *weight > 1 {
+            writeln!(writer, "  {} -- {} [label=\"{}\"];", graph.nodes[*from].id, graph.nodes[*to].id, weight)?;
+        } else {
+            writeln!(writer, "  {} -- {};", graph.nodes[*from].id, graph.nodes[*to].id)?;
+        }
+    }
+    writeln!(writer, "}}")
+}
+
+pub fn write_graphml<W: Write>(mut writer: W, graph: &Graph) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+    writeln!(writer, r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#)?;
+    writeln!(writer, r#"  <key id="kind" for="node" attr.name="kind" attr.type="string"/>"#)?;
+    writeln!(writer, r#"  <key id="public" for="node" attr.name="public" attr.type="boolean"/>"#)?;
+    writeln!(writer, r#"  <key id="weight" for="edge" attr.name="weight" attr.type="int"/>"#)?;
+    writeln!(writer, r#"  <graph id="circuit" edgedefault="undirected">"#)?;
+    for node in graph.nodes.iter() {
+        let kind = match node.kind {
+            NodeKind::Wire => "wire",
+            NodeKind::Constraint => "constraint",
+            NodeKind::Group => "group",
+        };
+        writeln!(writer, r#"    <node id="{}"><data key="label">{}</data><data key="kind">{}</data><data key="public">{}</data></node>"#,
+            node.id, escape_xml(&node.label), kind, node.public)?;
+    }
+    for (from, to, weight) in graph.edges.iter() {
+        writeln!(writer, r#"    <edge source="{}" target="{}"><data key="weight">{}</data></edge>"#,
+            graph.nodes[*from].id, graph.nodes[*to].id, weight)?;
+    }
+    writeln!(writer, "  </graph>")?;
+    writeln!(writer, "</graphml>")
+}
+
+pub fn write_graph<W: Write>(writer: W, graph: &Graph, format: GraphFormat) -> io::Result<()> {
+    match format {
+        GraphFormat::Dot => write_dot(writer, graph),
+        GraphFormat::GraphML => write_graphml(writer, graph),
+    }
+}
+
+#[test]
+fn graph_collapse_and_around() {
+    use bellman_ce::pairing::{ff::{Field, PrimeField}, bn256::{Bn256, Fr}};
+    let one = Fr::one();
+
+    // chain: out = a * a, a = b * b, b = x * x
+    let r1cs = R1CS::<Bn256> {
+        num_inputs: 2,
+        num_aux: 3,
+        num_variables: 5,
+        constraints: vec![
+            (vec![(2, one)], vec![(2, one)], vec![(1, one)]),
+            (vec![(3, one)], vec![(3, one)], vec![(2, one), (0, Fr::from_str("0").unwrap())]),
+            (vec![(4, one)], vec![(4, one)], vec![(3, one)]),
+        ],
+    };
+    let names = vec![None, Some("main.out".to_string()), Some("main.sq.a".to_string()), Some("main.sq.b".to_string())];
+
+    let graph = build_graph(&r1cs, &names, &GraphOptions::default());
+    assert_eq!((graph.nodes.len(), graph.edges.len()), (7, 6));
+    assert!(graph.nodes.iter().any(|n| n.label == "main.out" && n.public));
+
+    let graph = build_graph(&r1cs, &names, &GraphOptions { around: Some((1, 1)), ..GraphOptions::default() });
+    assert_eq!((graph.nodes.len(), graph.edges.len()), (3, 2));
+
+    let graph = build_graph(&r1cs, &names, &GraphOptions { collapse: Some(2), ..GraphOptions::default() });
+    let labels = graph.nodes.iter().map(|n| n.label.as_str()).collect::<Vec<_>>();
+    assert_eq!(labels, vec!["main.out", "main.sq", "w4"]);
+    assert_eq!(graph.edges, vec![(0, 1, 1), (1, 2, 1)]);
+
+    let mut data = vec![];
+    write_dot(&mut data, &graph).unwrap();
+    assert!(String::from_utf8(data).unwrap().contains("g0 [label=\"main.out\", style=filled, fillcolor=lightblue];"));
+}
@@ -12,4 +12,33 @@ extern crate num_traits;
 pub mod utils;
 pub mod circom_circuit;
 pub mod r1cs_reader;
+pub mod r1cs_writer;
+pub mod r1cs_stream;
+pub mod csr;
+pub mod parallel_loader;
+pub mod optimizer;
+pub mod lint;
+pub mod fuzzer;
+pub mod solver;
+pub mod r1cs_diff;
+pub mod smt;
+pub mod graph;
+pub mod recorder;
 pub mod wtns_reader;
+pub mod wtns_writer;
+pub mod sym_reader;
+pub mod sym_writer;
+pub mod witness_view;
+pub mod public_signals;
+pub mod circuit_info;
+pub mod params_info;
+pub mod prover;
+pub mod verifier;
+pub mod server;
+pub mod ffi;
+#[cfg(test)]
+mod fixture;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "node")]
+pub mod node;
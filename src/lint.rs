@@ -0,0 +1,141 @@
+//! Static checks for common under-constrained circuit bugs
+extern crate bellman_ce;
+
+use std::fmt;
+use bellman_ce::pairing::{
+    Engine,
+    ff::Field,
+};
+
+use crate::circom_circuit::R1CS;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    /// Private wire that appears in no constraint, any value is accepted
+    UnusedWire,
+    /// Private wire that appears linearly in a single constraint only
+    SingleLinearUse,
+    /// Public input that appears in no constraint
+    UnconstrainedInput,
+    /// Constraint with empty A or B side, it's `0 = C`
+    EmptySide,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub kind: LintKind,
+    pub wire: Option<usize>,
+    pub constraint: Option<usize>,
+    /// Signal name from .sym file
+    pub name: Option<String>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let wire = match (self.wire, &self.name) {
+            (Some(wire), Some(name)) => format!("wire {} ({})", wire, name),
+            (Some(wire), None) => format!("wire {}", wire),
+            _ => String::new(),
+        };
+        let constraint = self.constraint.unwrap_or_default();
+        match self.kind {
+            LintKind::UnusedWire => write!(f, "unused-wire: {} is not used in any constraint", wire),
+            LintKind::SingleLinearUse => write!(f, "single-linear-use: {} only appears linearly in constraint {}", wire, constraint),
+            LintKind::UnconstrainedInput => write!(f, "unconstrained-input: public {} is not used in any constraint", wire),
+            LintKind::EmptySide => write!(f, "empty-side: constraint {} has empty A or B side", constraint),
+        }
+    }
+}
+
+fn is_empty<E: Engine>(lc: &[(usize, E::Fr)]) -> bool {
+    lc.iter().all(|(_, coeff)| coeff.is_zero())
+}
+
+fn is_constant<E: Engine>(lc: &[(usize, E::Fr)]) -> bool {
+    lc.iter().all(|(index, coeff)| *index == 0 || coeff.is_zero())
+}
+
+/// Runs all checks, `names` are optional signal names indexed by wire
+pub fn lint<E: Engine>(r1cs: &R1CS<E>, names: &[Option<String>]) -> Vec<Finding> {
+    // number of constraints using each wire and the last one of them
+    let mut uses = vec![0usize; r1cs.num_variables];
+    let mut last_use = vec![0usize; r1cs.num_variables];
+    let mut nonlinear = vec![false; r1cs.num_variables];
+    let mut findings = vec![];
+    let finding = |kind, wire: Option<usize>, constraint| Finding {
+        kind,
+        wire,
+        constraint,
+        name: wire.and_then(|w| names.get(w).cloned().flatten()),
+    };
+
+    for (i, (a, b, c)) in r1cs.constraints.iter().enumerate() {
+        if is_empty::<E>(a) || is_empty::<E>(b) {
+            findings.push(finding(LintKind::EmptySide, None, Some(i)));
+        }
+        let (a_const, b_const) = (is_constant::<E>(a), is_constant::<E>(b));
+        let mut wires = vec![];
+        for (index, coeff) in a.iter().chain(b.iter()).chain(c.iter()) {
+            if !coeff.is_zero() {
+                wires.push(*index);
+            }
+        }
+        for (index, coeff) in a.iter() {
+            if !b_const && !coeff.is_zero() {
+                nonlinear[*index] = true;
+            }
+        }
+        for (index, coeff) in b.iter() {
+            if !a_const && !coeff.is_zero() {
+                nonlinear[*index] = true;
+            }
+        }
+        wires.sort_unstable();
+        wires.dedup();
+        for index in wires {
+            uses[index] += 1;
+            last_use[index] = i;
+        }
+    }
+
+    for wire in 1..r1cs.num_variables {
+        let public = wire < r1cs.num_inputs;
+        if uses[wire] == 0 {
+            let kind = if public { LintKind::UnconstrainedInput } else { LintKind::UnusedWire };
+            findings.push(finding(kind, Some(wire), None));
+        } else if !public && uses[wire] == 1 && !nonlinear[wire] {
+            findings.push(finding(LintKind::SingleLinearUse, Some(wire), Some(last_use[wire])));
+        }
+    }
+    findings
+}
+
+#[test]
+fn lint_findings() {
+    use bellman_ce::pairing::bn256::{Bn256, Fr};
+    let one = || Fr::one();
+
+    // wires: 1, out, in, x, y, unused; out = x * x, y = in + x, 0 = x
+    let r1cs = R1CS::<Bn256> {
+        num_inputs: 3,
+        num_aux: 3,
+        num_variables: 6,
+        constraints: vec![
+            (vec![(3, one())], vec![(3, one())], vec![(1, one())]),
+            (vec![(0, one())], vec![(3, one()), (2, Fr::zero())], vec![(4, one())]),
+            (vec![], vec![(0, one())], vec![(3, one())]),
+        ],
+    };
+    let names = vec![None, Some("main.out".to_string())];
+    let findings = lint(&r1cs, &names);
+    let kinds = findings.iter().map(|f| (f.kind, f.wire, f.constraint)).collect::<Vec<_>>();
+    assert_eq!(kinds, vec![
+        (LintKind::EmptySide, None, Some(2)),
+        (LintKind::UnconstrainedInput, Some(2), None),
+        (LintKind::SingleLinearUse, Some(4), Some(1)),
+        (LintKind::UnusedWire, Some(5), None),
+    ]);
+    assert_eq!(lint(&r1cs, &[])[1].to_string(), "unconstrained-input: public wire 2 is not used in any constraint");
+    assert!(lint(&crate::fixture::fixture(3).circuit.r1cs, &names).is_empty());
+}
@@ -3,30 +3,71 @@ extern crate bellman_ce;
 extern crate zkutil;
 
 use std::fs;
-use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
 use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
 use clap::Clap;
 use bellman_ce::pairing::{
     Engine,
-    bn256::Bn256
+    ff::PrimeField,
+    bn256::{Bn256, Fr},
+};
+use zkutil::utils::{repr_to_big, synthesis_error_message};
+use zkutil::fuzzer::WitnessFuzzer;
+use zkutil::circuit_info::load_circuit_info_file;
+use zkutil::lint;
+use zkutil::optimizer::{self, try_remap_witness};
+use zkutil::r1cs_reader::{self, R1CSFile};
+use zkutil::r1cs_writer::{self, bn256_header};
+use zkutil::r1cs_stream::{R1CSStream, StreamingCircuit};
+use zkutil::r1cs_diff::{diff_r1cs_files, format_lc};
+use zkutil::smt::{SmtEncoding, SmtOptions, write_smt};
+use zkutil::graph::{GraphFormat, GraphOptions, build_graph, write_graph};
+use zkutil::solver::{self, SolveError, inputs_from_json};
+use zkutil::wtns_writer;
+use zkutil::sym_reader::SymEntry;
+use zkutil::witness_view::{diff_witnesses, format_value, index_names, witness_entries};
+use zkutil::public_signals::{check_expected_public, expected_public_from_json, labeled_public_json, load_public_json_file};
+use zkutil::sym_reader;
+use zkutil::params_info::load_params_info_file;
+use zkutil::prover::{load_prover_file, load_prover_file_parallel};
+use zkutil::parallel_loader::{self, default_threads};
+use zkutil::server::{ProvingServer, ServerOptions};
+use zkutil::verifier::{
+    Verifier,
+    load_verifier_file,
+    save_verifier_file,
 };
 use zkutil::circom_circuit::{
-    prove as prove2,
-    verify as verify2,
+    load_proof_batch_jsonl_file,
     create_rng,
     load_params_file,
+    load_params_file_with_header,
+    write_params_file,
+    filter_params,
+    ParamsHeader,
+    check_circuit_hash,
     proof_to_json_file,
+    public_inputs_json,
     r1cs_from_json_file,
     r1cs_from_bin_file,
-    witness_from_json_file,
+    try_r1cs_from_json_file,
+    try_witness_from_json_file,
     witness_from_bin_file,
     load_proof_json_file,
-    load_inputs_json_file,
+    parse_inputs_json,
+    parse_proof_json,
     create_verifier_sol_file,
     proving_key_json_file,
     verification_key_json_file,
     generate_random_parameters,
     CircomCircuit,
+    CircomCircuitRef,
+    Constraint,
+    ProofWithInputs,
     R1CS,
 };
 
@@ -41,14 +82,42 @@ struct Opts {
 enum SubCommand {
     /// Generate a SNARK proof
     Prove(ProveOpts),
+    /// Generate SNARK proofs for many witnesses
+    ProveBatch(ProveBatchOpts),
     /// Verify a SNARK proof
     Verify(VerifyOpts),
+    /// Verify many SNARK proofs at once
+    VerifyBatch(VerifyBatchOpts),
     /// Generate trusted setup parameters
     Setup(SetupOpts),
     /// Generate verifier smart contract
     GenerateVerifier(GenerateVerifierOpts),
     /// Export proving and verifying keys compatible with snarkjs/websnark
     ExportKeys(ExportKeysOpts),
+    /// Export prepared verifying key for fast proof verification
+    PrepareVk(PrepareVkOpts),
+    /// Work with trusted setup parameters files
+    Params(ParamsOpts),
+    /// Run a proving server with HTTP/JSON API
+    Serve(ServeOpts),
+    /// Show circuit size and resource estimates
+    Info(InfoOpts),
+    /// Optimize circuit constraints
+    Optimize(OptimizeOpts),
+    /// Check circuit for under-constrained signals
+    Lint(LintOpts),
+    /// Search for alternative witnesses by mutating private signals
+    FuzzWitness(FuzzWitnessOpts),
+    /// Compare two circuits
+    Diff(DiffOpts),
+    /// Export circuit constraints to SMT-LIB
+    ExportSmt(ExportSmtOpts),
+    /// Export wire/constraint graph as DOT or GraphML
+    Graph(GraphOpts),
+    /// Calculate witness from inputs for straight-line circuits
+    Solve(SolveOpts),
+    /// Inspect witness files
+    Witness(WitnessOpts),
 }
 
 /// A subcommand for generating a SNARK proof
@@ -69,6 +138,48 @@ struct ProveOpts {
     /// Output file for public inputs JSON
     #[clap(short = "o", long = "public", default_value = "public.json")]
     public: String,
+    /// Wire map JSON file from `optimize`, remaps witness of the original circuit
+    #[clap(short = "m", long = "wire-map")]
+    wire_map: Option<String>,
+    /// Output file for public signals JSON labeled with names and output/input kind, e.g. public.labeled.json
+    #[clap(short = "l", long = "labeled-public")]
+    labeled_public: Option<String>,
+    /// Circuit sym file for labeled public signals [default: circuit file with .sym extension]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Expected public signals JSON, full public.json or an object keyed by wire index or signal name
+    #[clap(short = "e", long = "expect-public")]
+    expect_public: Option<String>,
+    /// Read constraints from the .r1cs file during proving instead of loading the whole circuit into memory
+    #[clap(long = "streaming")]
+    streaming: bool,
+    /// Threads for loading params and circuit [default: number of CPUs]
+    #[clap(short = "t", long = "threads")]
+    threads: Option<usize>,
+}
+
+/// A subcommand for generating SNARK proofs for many witnesses
+#[derive(Clap)]
+struct ProveBatchOpts {
+    /// Snark trusted setup parameters file
+    #[clap(short = "p", long = "params", default_value = "params.bin")]
+    params: String,
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Directory with witness .wtns or .json files
+    #[clap(short = "w", long = "witness-dir")]
+    witness_dir: String,
+    /// Output directory for proof_N.json, public_N.json and summary.json
+    #[clap(short = "o", long = "output-dir", default_value = ".")]
+    output_dir: String,
+    /// Number of witnesses proved in parallel. Every proof already runs on all CPUs, so more
+    /// workers mostly add memory use and only help when single proofs can't keep the CPUs busy
+    #[clap(short = "j", long = "workers", default_value = "1")]
+    workers: usize,
+    /// Wire map JSON file from `optimize`, remaps witnesses of the original circuit
+    #[clap(short = "m", long = "wire-map")]
+    wire_map: Option<String>,
 }
 
 /// A subcommand for verifying a SNARK proof
@@ -83,6 +194,32 @@ struct VerifyOpts {
     /// Public inputs JSON file
     #[clap(short = "i", long = "public", default_value = "public.json")]
     public: String,
+    /// Prepared verifying key file, used instead of params if specified
+    #[clap(short = "k", long = "prepared-vk")]
+    prepared_vk: Option<String>,
+    /// Circuit R1CS or JSON file to check labeled public signal names against [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Circuit sym file [default: circuit file with .sym extension]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+}
+
+/// A subcommand for verifying many SNARK proofs at once
+#[derive(Clap)]
+struct VerifyBatchOpts {
+    /// Snark trusted setup parameters file
+    #[clap(short = "p", long = "params", default_value = "params.bin")]
+    params: String,
+    /// Directory with proof_N.json and public_N.json pairs
+    #[clap(short = "d", long = "dir", required_unless = "jsonl")]
+    dir: Option<String>,
+    /// JSONL file with one {"proof": ..., "public": ...} object per line
+    #[clap(short = "j", long = "jsonl", conflicts_with = "dir")]
+    jsonl: Option<String>,
+    /// Prepared verifying key file, used instead of params if specified
+    #[clap(short = "k", long = "prepared-vk")]
+    prepared_vk: Option<String>,
 }
 
 /// A subcommand for generating a trusted setup parameters
@@ -105,6 +242,9 @@ struct GenerateVerifierOpts {
     /// Output smart contract name
     #[clap(short = "v", long = "verifier", default_value = "Verifier.sol")]
     verifier: String,
+    /// Circuit R1CS or JSON file, if specified params are checked to be generated for it
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
 }
 
 /// A subcommand for exporting proving and verifying keys compatible with snarkjs/websnark
@@ -124,15 +264,282 @@ struct ExportKeysOpts {
     vk: String,
 }
 
+/// A subcommand for exporting a prepared verifying key
+#[derive(Clap)]
+struct PrepareVkOpts {
+    /// Snark trusted setup parameters file
+    #[clap(short = "p", long = "params", default_value = "params.bin")]
+    params: String,
+    /// Output prepared verifying key file
+    #[clap(short = "k", long = "prepared-vk", default_value = "prepared_vk.bin")]
+    prepared_vk: String,
+    /// Circuit R1CS or JSON file, if specified params are checked to be generated for it
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+}
+
+/// A subcommand for working with trusted setup parameters files
+#[derive(Clap)]
+struct ParamsOpts {
+    #[clap(subcommand)]
+    command: ParamsSubCommand,
+}
+
+#[derive(Clap)]
+enum ParamsSubCommand {
+    /// Upgrade legacy parameters file to the current format
+    Normalize(ParamsNormalizeOpts),
+    /// Show verifying key and sizes of parameters file contents
+    Info(ParamsInfoOpts),
+}
+
+/// A subcommand for upgrading legacy parameters file to the current format
+#[derive(Clap)]
+struct ParamsNormalizeOpts {
+    /// Snark trusted setup parameters file
+    #[clap(short = "p", long = "params", default_value = "params.bin")]
+    params: String,
+    /// Output parameters file [default: overwrite input file]
+    #[clap(short = "o", long = "output")]
+    output: Option<String>,
+}
+
+/// A subcommand for showing parameters file contents
+#[derive(Clap)]
+struct ParamsInfoOpts {
+    /// Snark trusted setup parameters file
+    #[clap(default_value = "params.bin")]
+    params: String,
+    /// Print info as JSON
+    #[clap(long = "json")]
+    json: bool,
+}
+
+/// A subcommand for running a proving server with HTTP/JSON API
+#[derive(Clap)]
+struct ServeOpts {
+    /// Snark trusted setup parameters file
+    #[clap(short = "p", long = "params", default_value = "params.bin")]
+    params: String,
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Address to listen on
+    #[clap(short = "a", long = "address", default_value = "127.0.0.1:8080")]
+    address: String,
+    /// Number of proofs generated in parallel
+    #[clap(short = "j", long = "workers", default_value = "1")]
+    workers: usize,
+    /// Max number of proving requests waiting in the queue
+    #[clap(short = "q", long = "queue-size", default_value = "16")]
+    queue_size: usize,
+    /// Circom symbols file for signal names in input requests [default: circuit file with .sym extension, if exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Max request body size in MiB
+    #[clap(long = "max-body-size", default_value = "64")]
+    max_body_size: u64,
+    /// Wire map JSON file from `optimize`, remaps witnesses of the original circuit in `witness` requests
+    #[clap(short = "m", long = "wire-map")]
+    wire_map: Option<String>,
+}
+
+/// A subcommand for showing circuit size and resource estimates
+#[derive(Clap)]
+struct InfoOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    circuit: Option<String>,
+    /// Print info as JSON
+    #[clap(long = "json")]
+    json: bool,
+}
+
+/// A subcommand for optimizing circuit constraints
+#[derive(Clap)]
+struct OptimizeOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Output file for optimized circuit R1CS
+    #[clap(short = "o", long = "output", default_value = "circuit.opt.r1cs")]
+    output: String,
+    /// Output file for wire map JSON, original wire index for every optimized wire
+    #[clap(short = "m", long = "wire-map", default_value = "wire_map.json")]
+    wire_map: String,
+}
+
+/// A subcommand for checking circuit for under-constrained signals
+#[derive(Clap)]
+struct LintOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Circom symbols file for signal names [default: circuit file with .sym extension, if exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Print findings as JSON
+    #[clap(long = "json")]
+    json: bool,
+}
+
+/// A subcommand for searching alternative witnesses with the same public inputs
+#[derive(Clap)]
+struct FuzzWitnessOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Valid witness to mutate [default: witness.wtns|witness.json]
+    #[clap(short = "w", long = "witness")]
+    witness: Option<String>,
+    /// Circom symbols file for signal names [default: circuit file with .sym extension, if exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Number of random mutations, private wires are mutated in turn after trying -x, 0, 1 and -1 for each of them
+    #[clap(short = "n", long = "iterations", default_value = "1000")]
+    iterations: usize,
+}
+
+/// A subcommand for comparing two circuits
+#[derive(Clap)]
+struct DiffOpts {
+    /// Old circuit R1CS or JSON file
+    old: String,
+    /// New circuit R1CS or JSON file
+    new: String,
+    /// Print diff as JSON
+    #[clap(long = "json")]
+    json: bool,
+}
+
+/// A subcommand for exporting circuit constraints to SMT-LIB
+#[derive(Clap)]
+struct ExportSmtOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    circuit: Option<String>,
+    /// Output file for SMT-LIB script
+    #[clap(short = "o", long = "output", default_value = "circuit.smt2")]
+    output: String,
+    /// Finite field theory or integers modulo the field prime
+    #[clap(short = "e", long = "encoding", default_value = "ff", possible_values = &["ff", "int"])]
+    encoding: String,
+    /// Comma separated wires to treat as public [default: all public inputs]
+    #[clap(long = "public")]
+    public: Option<String>,
+    /// Add a second witness copy, sat result means there is an alternative witness
+    #[clap(short = "u", long = "uniqueness")]
+    uniqueness: bool,
+}
+
+/// A subcommand for exporting wire/constraint graph
+#[derive(Clap)]
+struct GraphOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    circuit: Option<String>,
+    /// Output file [default: circuit.dot|circuit.graphml]
+    #[clap(short = "o", long = "output")]
+    output: Option<String>,
+    /// Output format
+    #[clap(short = "f", long = "format", default_value = "dot", possible_values = &["dot", "graphml"])]
+    format: String,
+    /// Circom symbols file for signal names [default: circuit file with .sym extension, if exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Collapse wires by sym name prefix with this many components, e.g. 2 for main.hasher
+    #[clap(long = "collapse")]
+    collapse: Option<usize>,
+    /// Only show the neighborhood of this wire, given by index or sym name
+    #[clap(long = "around")]
+    around: Option<String>,
+    /// Number of constraint hops from --around wire
+    #[clap(long = "radius", default_value = "2")]
+    radius: usize,
+}
+
+/// A subcommand for calculating witness from inputs
+#[derive(Clap)]
+struct SolveOpts {
+    /// Circuit R1CS or JSON file [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Inputs JSON file keyed by wire index or signal name
+    #[clap(short = "i", long = "input", default_value = "input.json")]
+    input: String,
+    /// Circom symbols file for signal names [default: circuit file with .sym extension, if exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Output witness .wtns or .json file
+    #[clap(short = "w", long = "witness", default_value = "witness.wtns")]
+    witness: String,
+}
+
+/// A subcommand for inspecting witness files
+#[derive(Clap)]
+struct WitnessOpts {
+    #[clap(subcommand)]
+    command: WitnessSubCommand,
+}
+
+#[derive(Clap)]
+enum WitnessSubCommand {
+    /// Show witness values with signal names
+    Show(WitnessShowOpts),
+    /// Compare two witnesses for the same circuit
+    Diff(WitnessDiffOpts),
+}
+
+/// A subcommand for showing witness values with signal names
+#[derive(Clap)]
+struct WitnessShowOpts {
+    /// Witness .wtns or .json file [default: witness.wtns|witness.json]
+    witness: Option<String>,
+    /// Circuit R1CS or JSON file, used to tell if witness is indexed by wire or signal label [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Circom symbols file for signal names [default: circuit file with .sym extension, if exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Only show signals matching the pattern, e.g. main.hasher.*. Without a sym file the pattern
+    /// is matched against wire indices, e.g. 1?
+    #[clap(short = "f", long = "filter")]
+    filter: Option<String>,
+    /// Print values in hex
+    #[clap(long = "hex")]
+    hex: bool,
+}
+
+/// A subcommand for comparing two witnesses
+#[derive(Clap)]
+struct WitnessDiffOpts {
+    /// First witness .wtns or .json file
+    a: String,
+    /// Second witness .wtns or .json file
+    b: String,
+    /// Circuit R1CS or JSON file, used to tell if witness is indexed by wire or signal label [default: circuit.r1cs|circuit.json]
+    #[clap(short = "c", long = "circuit")]
+    circuit: Option<String>,
+    /// Circom symbols file for signal names [default: circuit file with .sym extension, if exists]
+    #[clap(short = "s", long = "sym")]
+    sym: Option<String>,
+    /// Print values in hex
+    #[clap(long = "hex")]
+    hex: bool,
+}
+
 fn main() {
     let opts: Opts = Opts::parse();
     match opts.command {
         SubCommand::Prove(o) => {
             prove(o);
         }
+        SubCommand::ProveBatch(o) => {
+            prove_batch(o);
+        }
         SubCommand::Verify(o) => {
             verify(o);
         }
+        SubCommand::VerifyBatch(o) => {
+            verify_batch_cmd(o);
+        }
         SubCommand::Setup(o) => {
             setup(o);
         }
@@ -142,6 +549,52 @@ fn main() {
         SubCommand::ExportKeys(o) => {
             export_keys(o);
         }
+        SubCommand::PrepareVk(o) => {
+            prepare_vk(o);
+        }
+        SubCommand::Serve(o) => {
+            serve(o);
+        }
+        SubCommand::Info(o) => {
+            info(o);
+        }
+        SubCommand::Optimize(o) => {
+            optimize(o);
+        }
+        SubCommand::Lint(o) => {
+            lint(o);
+        }
+        SubCommand::FuzzWitness(o) => {
+            fuzz_witness(o);
+        }
+        SubCommand::Diff(o) => {
+            diff(o);
+        }
+        SubCommand::ExportSmt(o) => {
+            export_smt(o);
+        }
+        SubCommand::Graph(o) => {
+            graph(o);
+        }
+        SubCommand::Solve(o) => {
+            solve(o);
+        }
+        SubCommand::Witness(o) => match o.command {
+            WitnessSubCommand::Show(o) => {
+                witness_show(o);
+            }
+            WitnessSubCommand::Diff(o) => {
+                witness_diff(o);
+            }
+        },
+        SubCommand::Params(o) => match o.command {
+            ParamsSubCommand::Normalize(o) => {
+                params_normalize(o);
+            }
+            ParamsSubCommand::Info(o) => {
+                params_info(o);
+            }
+        },
     }
 }
 
@@ -165,11 +618,11 @@ fn resolve_circuit_file(filename: Option<String>) -> String {
     }
 }
 
-fn load_witness<E: Engine>(filename: &str) -> Vec<E::Fr> {
+fn load_witness<E: Engine>(filename: &str) -> io::Result<Vec<E::Fr>> {
     if filename.ends_with("json") {
-        witness_from_json_file::<E>(filename)
+        try_witness_from_json_file::<E>(filename)
     } else {
-        witness_from_bin_file::<E>(filename).unwrap()
+        witness_from_bin_file::<E>(filename)
     }
 }
 
@@ -184,29 +637,266 @@ fn resolve_witness_file(filename: Option<String>) -> String {
     }
 }
 
+/// Sym file defaults to the circuit file with .sym extension if it exists
+fn resolve_sym_file(circuit_file: &str, sym: Option<String>) -> Option<String> {
+    sym.or_else(|| {
+        let path = Path::new(circuit_file).with_extension("sym");
+        path.exists().then(|| path.to_string_lossy().to_string())
+    })
+}
+
+/// Number of wires and the header of a .r1cs file, only JSON circuits are loaded in full
+fn load_circuit_header(filename: &str) -> io::Result<(usize, Option<r1cs_reader::Header>)> {
+    if filename.ends_with("json") {
+        return Ok((try_r1cs_from_json_file::<Bn256>(filename)?.num_variables, None));
+    }
+    let layout = r1cs_reader::read_layout(BufReader::new(fs::File::open(filename)?))?;
+    Ok((layout.header.n_wires as usize, Some(layout.header)))
+}
+
+fn load_wire_names(circuit_file: &str, sym: Option<String>, num_wires: usize) -> Vec<Option<String>> {
+    match resolve_sym_file(circuit_file, sym) {
+        Some(filename) => sym_reader::wire_names(&sym_reader::read_file(&filename).unwrap(), num_wires),
+        None => vec![],
+    }
+}
+
+fn wire_name(names: &[Option<String>], wire: usize) -> String {
+    match names.get(wire).cloned().flatten() {
+        Some(name) => format!("wire {} ({})", wire, name),
+        None => format!("wire {}", wire),
+    }
+}
+
+/// Exits if params were generated for a different circuit
+fn check_circuit(header: &ParamsHeader, r1cs: &R1CS<Bn256>) {
+    if let Err(e) = check_circuit_hash(header, r1cs) {
+        println!("{}", e);
+        std::process::exit(400);
+    }
+}
+
+/// Same as `check_circuit` for an optional circuit file
+fn check_circuit_file(header: &ParamsHeader, circuit_file: Option<String>) {
+    if let Some(circuit_file) = circuit_file {
+        check_circuit(header, &load_r1cs(&circuit_file));
+    }
+}
+
+fn load_verifier(params: &str, prepared_vk: &Option<String>) -> Verifier<Bn256> {
+    match prepared_vk {
+        Some(filename) => load_verifier_file(filename).unwrap(),
+        None => Verifier::new(&load_params_file(params).vk),
+    }
+}
+
+/// Exits with a per-signal diff if public signals don't match the expected JSON file
+fn expect_public(filename: &str, circuit_file: &str, sym: Option<String>, public: &[Fr], num_variables: usize) {
+    let names = load_wire_names(circuit_file, sym, num_variables);
+    let expected = serde_json::from_str(&fs::read_to_string(filename).unwrap()).unwrap();
+    let expected = expected_public_from_json::<Bn256>(&expected, &names).unwrap();
+    let mismatches = check_expected_public::<Bn256>(public, &expected, &names).unwrap();
+    if !mismatches.is_empty() {
+        println!("Public signals don't match expected values:");
+        for mismatch in mismatches.iter() {
+            println!("  {}", mismatch);
+        }
+        std::process::exit(400);
+    }
+}
+
+/// Progress counter on stderr, skipped when it isn't a terminal so logs don't fill up with it
+fn print_progress(section: &str, done: usize, total: usize) {
+    let mut stderr = io::stderr();
+    if !stderr.is_terminal() {
+        return;
+    }
+    eprint!("\r  {}: {}/{}", section, done, total);
+    if done == total {
+        eprintln!();
+    }
+    stderr.flush().unwrap();
+}
+
 fn prove(opts: ProveOpts) {
     let rng = create_rng();
-    let params = load_params_file(&opts.params);
+    let threads = opts.threads.unwrap_or_else(default_threads).max(1);
     let circuit_file = resolve_circuit_file(opts.circuit);
+    if opts.streaming && !circuit_file.ends_with(".r1cs") {
+        println!("--streaming needs a .r1cs circuit file, got {}", circuit_file);
+        std::process::exit(400);
+    }
+    println!("Loading {}...", opts.params);
+    let prover = load_prover_file_parallel(&opts.params, threads, &print_progress).unwrap();
     let witness_file = resolve_witness_file(opts.witness);
     println!("Loading circuit from {}...", circuit_file);
-    let circuit = CircomCircuit {
-        r1cs: load_r1cs(&circuit_file),
-        witness: Some(load_witness::<Bn256>(&witness_file)),
-        wire_mapping: None,
+    let mut witness = load_witness::<Bn256>(&witness_file).unwrap();
+    if let Some(filename) = opts.wire_map {
+        let wire_map = optimizer::load_wire_mapping_file(&filename).unwrap();
+        witness = match try_remap_witness(&witness, &wire_map) {
+            Ok(witness) => witness,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(400);
+            }
+        };
+    }
+    // constraints of a streamed circuit stay in the file, otherwise the whole circuit is loaded
+    let stream = opts.streaming.then(|| R1CSStream::open(&circuit_file).unwrap());
+    let loaded = match &stream {
+        Some(_) => None,
+        None => Some(parallel_loader::load_r1cs_file_with_header(&circuit_file, threads, &print_progress).unwrap()),
+    };
+    let num_variables = stream.as_ref().map_or_else(|| loaded.as_ref().unwrap().0.num_variables, |r1cs| r1cs.num_variables());
+    if witness.len() != num_variables {
+        println!("Witness has {} values, circuit has {} wires", witness.len(), num_variables);
+        std::process::exit(400);
+    }
+    let (proof, public, n_pub_out) = if let Some(r1cs) = &stream {
+        if let Err(e) = r1cs.check_circuit_hash::<Bn256>(prover.header()) {
+            println!("{}", e);
+            std::process::exit(400);
+        }
+        let circuit = StreamingCircuit { r1cs, witness: Some(&witness) };
+        let public = circuit.get_public_inputs().unwrap();
+        if let Some(filename) = &opts.expect_public {
+            expect_public(filename, &circuit_file, opts.sym.clone(), &public, num_variables);
+        }
+        println!("Proving...");
+        let proof = prover.prove(circuit, rng).unwrap();
+        (proof, public, Some(r1cs.header().n_pub_out as usize))
+    } else {
+        let (r1cs, header) = loaded.unwrap();
+        let circuit = CircomCircuit {
+            r1cs,
+            witness: Some(witness),
+            wire_mapping: None,
+        };
+        check_circuit(prover.header(), &circuit.r1cs);
+        let public = circuit.get_public_inputs().unwrap();
+        if let Some(filename) = &opts.expect_public {
+            expect_public(filename, &circuit_file, opts.sym.clone(), &public, num_variables);
+        }
+        println!("Proving...");
+        let proof = prover.prove(circuit.by_ref(), rng).unwrap();
+        (proof, public, header.map(|h| h.n_pub_out as usize))
     };
-    println!("Proving...");
-    let proof = prove2(circuit.clone(), &params, rng).unwrap();
     proof_to_json_file(&proof, &opts.proof).unwrap();
-    fs::write(&opts.public, circuit.get_public_inputs_json().as_bytes()).unwrap();
+    fs::write(&opts.public, public_inputs_json::<Bn256>(&public).as_bytes()).unwrap();
     println!("Saved {} and {}", opts.proof, opts.public);
+    if let Some(filename) = opts.labeled_public {
+        let names = load_wire_names(&circuit_file, opts.sym, num_variables);
+        fs::write(&filename, labeled_public_json::<Bn256>(&public, n_pub_out, &names).as_bytes()).unwrap();
+        println!("Saved {}", filename);
+    }
+}
+
+fn prove_batch(opts: ProveBatchOpts) {
+    let mut witness_files = fs::read_dir(&opts.witness_dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wtns" || ext == "json"))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    witness_files.sort();
+    let workers = opts.workers.max(1);
+
+    println!("Loading {}...", opts.params);
+    let prover = load_prover_file_parallel(&opts.params, default_threads(), &print_progress).unwrap();
+    let circuit_file = resolve_circuit_file(opts.circuit.clone());
+    println!("Loading circuit from {}...", circuit_file);
+    let (r1cs, _) = parallel_loader::load_r1cs_file_with_header(&circuit_file, default_threads(), &print_progress).unwrap();
+    check_circuit(prover.header(), &r1cs);
+    let wire_map = opts.wire_map.as_ref().map(|filename| optimizer::load_wire_mapping_file(filename).unwrap());
+    fs::create_dir_all(&opts.output_dir).unwrap();
+
+    println!("Proving {} witnesses using {} workers...", witness_files.len(), workers);
+    let started = Instant::now();
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![]);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= witness_files.len() {
+                    break;
+                }
+                let proof_started = Instant::now();
+                let proof_file = format!("{}/proof_{}.json", opts.output_dir, i);
+                let public_file = format!("{}/public_{}.json", opts.output_dir, i);
+                let result = load_witness::<Bn256>(&witness_files[i])
+                    .and_then(|witness| match &wire_map {
+                        Some(wire_map) => try_remap_witness(&witness, wire_map),
+                        None => Ok(witness),
+                    })
+                    .map_err(|e| e.to_string())
+                    .and_then(|witness| if witness.len() == r1cs.num_variables {
+                        Ok(witness)
+                    } else {
+                        Err(format!("Witness has {} values, circuit has {} wires", witness.len(), r1cs.num_variables))
+                    })
+                    .and_then(|witness| {
+                        let circuit = CircomCircuitRef {
+                            r1cs: &r1cs,
+                            witness: Some(&witness),
+                            wire_mapping: None,
+                        };
+                        let proof = prover.prove(circuit, create_rng()).map_err(|e| synthesis_error_message(&e))?;
+                        proof_to_json_file(&proof, &proof_file).unwrap();
+                        fs::write(&public_file, circuit.get_public_inputs_json().as_bytes()).unwrap();
+                        Ok(())
+                    });
+                match &result {
+                    Ok(_) => println!("Saved {} and {}", proof_file, public_file),
+                    Err(e) => println!("Failed to prove {}: {}", witness_files[i], e),
+                }
+                results.lock().unwrap().push(serde_json::json!({
+                    "index": i,
+                    "witness": witness_files[i],
+                    "proof": result.as_ref().ok().map(|_| &proof_file),
+                    "public": result.as_ref().ok().map(|_| &public_file),
+                    "error": result.as_ref().err(),
+                    "time_ms": proof_started.elapsed().as_millis() as u64,
+                }));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|r| r["index"].as_u64());
+    let failed = results.iter().filter(|r| !r["error"].is_null()).count();
+    let summary = serde_json::json!({
+        "total": results.len(),
+        "failed": failed,
+        "workers": workers,
+        "time_ms": started.elapsed().as_millis() as u64,
+        "proofs": results,
+    });
+    let summary_file = format!("{}/summary.json", opts.output_dir);
+    fs::write(&summary_file, serde_json::to_string_pretty(&summary).unwrap().as_bytes()).unwrap();
+    println!("Saved {}, {} of {} proofs failed", summary_file, failed, summary["total"]);
+    if failed > 0 {
+        std::process::exit(400);
+    }
 }
 
 fn verify(opts: VerifyOpts) {
-    let params = load_params_file(&opts.params);
+    let verifier = load_verifier(&opts.params, &opts.prepared_vk);
     let proof = load_proof_json_file::<Bn256>(&opts.proof);
-    let inputs = load_inputs_json_file::<Bn256>(&opts.public);
-    let correct = verify2(&params, &proof, &inputs).unwrap();
+    let (inputs, labeled) = load_public_json_file::<Bn256>(&opts.public).unwrap();
+    if let Some(labeled) = labeled {
+        let circuit_file = resolve_circuit_file(opts.circuit);
+        if Path::new(&circuit_file).exists() {
+            let (num_wires, header) = load_circuit_header(&circuit_file).unwrap();
+            let names = load_wire_names(&circuit_file, opts.sym, num_wires);
+            if let Err(e) = labeled.check_names(header.map(|h| h.n_pub_out as usize), &names) {
+                println!("{}", e);
+                std::process::exit(400);
+            }
+        } else {
+            println!("Warning: {} not found, labeled public signal names are not checked", circuit_file);
+        }
+    }
+    let correct = verifier.verify(&proof, &inputs).unwrap();
     if correct {
         println!("Proof is correct");
     } else {
@@ -215,6 +905,70 @@ fn verify(opts: VerifyOpts) {
     }
 }
 
+/// Reads `proof_N.json` and `public_N.json` of one batch entry, errors name the file
+fn load_proof_batch_entry(dir: &str, i: usize) -> io::Result<ProofWithInputs<Bn256>> {
+    let read = |name: String| fs::read_to_string(format!("{}/{}", dir, name))
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", name, e)));
+    let proof = parse_proof_json(&read(format!("proof_{}.json", i))?)?;
+    let inputs = parse_inputs_json::<Bn256>(&read(format!("public_{}.json", i))?)?;
+    Ok((proof, inputs))
+}
+
+fn load_proof_batch_dir(dir: &str) -> io::Result<Vec<(String, io::Result<ProofWithInputs<Bn256>>)>> {
+    let mut indexes = vec![];
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(i) = name.strip_prefix("proof_").and_then(|n| n.strip_suffix(".json")).and_then(|n| n.parse::<usize>().ok()) {
+            indexes.push(i);
+        }
+    }
+    indexes.sort_unstable();
+    Ok(indexes.into_iter().map(|i| (format!("proof_{}.json", i), load_proof_batch_entry(dir, i))).collect())
+}
+
+fn verify_batch_cmd(opts: VerifyBatchOpts) {
+    let verifier = load_verifier(&opts.params, &opts.prepared_vk);
+    let entries = match (&opts.dir, &opts.jsonl) {
+        (Some(dir), _) => load_proof_batch_dir(dir),
+        (None, Some(jsonl)) => load_proof_batch_jsonl_file(jsonl).map(|proofs| {
+            proofs.into_iter().map(|(line, proof)| (format!("line {}", line), proof)).collect()
+        }),
+        (None, None) => unreachable!(),
+    };
+    let entries = entries.unwrap_or_else(|e| {
+        println!("Failed to load proofs: {}", e);
+        std::process::exit(400);
+    });
+    let mut names = vec![];
+    let mut proofs = vec![];
+    let mut failed = 0;
+    for (name, entry) in entries {
+        match entry {
+            Ok(proof) => {
+                names.push(name);
+                proofs.push(proof);
+            }
+            Err(e) => {
+                println!("Failed to load {}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+    println!("Verifying {} proofs...", proofs.len());
+    if verifier.verify_batch(&proofs).unwrap() {
+        if failed == 0 {
+            println!("All proofs are correct");
+            return;
+        }
+        println!("All loaded proofs are correct, {} failed to load", failed);
+    } else {
+        for i in verifier.find_invalid_proofs(&proofs).unwrap() {
+            println!("Proof is invalid: {}", names[i]);
+        }
+    }
+    std::process::exit(400);
+}
+
 fn setup(opts: SetupOpts) {
     let circuit_file = resolve_circuit_file(opts.circuit);
     println!("Loading circuit from {}...", circuit_file);
@@ -224,30 +978,389 @@ fn setup(opts: SetupOpts) {
         witness: None,
         wire_mapping: None,
     };
+    let circuit_hash = Some(circuit.r1cs.hash());
     println!("Generating trusted setup parameters...");
-    let params = generate_random_parameters(circuit, rng).unwrap();
+    let mut params = generate_random_parameters(circuit, rng).unwrap();
+    filter_params(&mut params);
     println!("Writing to file...");
-    let writer = File::create(&opts.params).unwrap();
-    params.write(writer).unwrap();
+    write_params_file(&params, &ParamsHeader { filtered: true, circuit_hash }, &opts.params).unwrap();
     println!("Saved parameters to {}", opts.params);
 }
 
 fn generate_verifier(opts: GenerateVerifierOpts) {
-    let params = load_params_file(&opts.params);
+    let (header, params) = load_params_file_with_header(&opts.params).unwrap();
+    check_circuit_file(&header, opts.circuit);
     create_verifier_sol_file(&params, &opts.verifier).unwrap();
     println!("Created {}", opts.verifier);
 }
 
 fn export_keys(opts: ExportKeysOpts) {
     println!("Exporting {}...", opts.params);
-    let params = load_params_file(&opts.params);
+    let (header, params) = load_params_file_with_header(&opts.params).unwrap();
     let circuit_file = resolve_circuit_file(opts.circuit);
     let circuit = CircomCircuit {
         r1cs: load_r1cs(&circuit_file),
         witness: None,
         wire_mapping: None,
     };
+    check_circuit(&header, &circuit.r1cs);
     proving_key_json_file(&params, circuit, &opts.pk).unwrap();
     verification_key_json_file(&params, &opts.vk).unwrap();
     println!("Created {} and {}.", opts.pk, opts.vk);
 }
+
+fn prepare_vk(opts: PrepareVkOpts) {
+    let (header, params) = load_params_file_with_header(&opts.params).unwrap();
+    check_circuit_file(&header, opts.circuit);
+    save_verifier_file(&Verifier::new(&params.vk), &opts.prepared_vk).unwrap();
+    println!("Created {}", opts.prepared_vk);
+}
+
+fn params_normalize(opts: ParamsNormalizeOpts) {
+    let (header, mut params) = load_params_file_with_header(&opts.params).unwrap();
+    let output = opts.output.clone().unwrap_or_else(|| opts.params.clone());
+    if header.filtered && output == opts.params {
+        println!("{} is already normalized", opts.params);
+        return;
+    }
+    filter_params(&mut params);
+    write_params_file(&params, &ParamsHeader { filtered: true, ..header }, &output).unwrap();
+    println!("Saved normalized parameters to {}", output);
+}
+
+fn params_info(opts: ParamsInfoOpts) {
+    let info = load_params_info_file(&opts.params).unwrap();
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&info).unwrap());
+        return;
+    }
+    println!("Params:         {}", opts.params);
+    println!("Filtered:       {}", if info.filtered { "yes" } else { "no" });
+    println!("VK hash:        {}", info.vk_hash);
+    println!("Circuit hash:   {}", info.circuit_hash.as_deref().unwrap_or("unknown"));
+    println!("Public inputs:  {} (IC length {})", info.public_inputs, info.ic);
+    println!("Lengths:        h {}, l {}, a {}, b_g1 {}, b_g2 {}", info.h, info.l, info.a, info.b_g1, info.b_g2);
+    println!("alpha_g1:       {:?}", info.alpha_g1);
+    println!("beta_g1:        {:?}", info.beta_g1);
+    println!("beta_g2:        {:?}", info.beta_g2);
+    println!("gamma_g2:       {:?}", info.gamma_g2);
+    println!("delta_g1:       {:?}", info.delta_g1);
+    println!("delta_g2:       {:?}", info.delta_g2);
+}
+
+fn serve(opts: ServeOpts) {
+    println!("Loading {}...", opts.params);
+    let prover = load_prover_file(&opts.params).unwrap();
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    println!("Loading circuit from {}...", circuit_file);
+    let r1cs = load_r1cs(&circuit_file);
+    check_circuit(prover.header(), &r1cs);
+    let options = ServerOptions {
+        workers: opts.workers,
+        queue_size: opts.queue_size,
+        names: load_wire_names(&circuit_file, opts.sym, r1cs.num_variables),
+        max_body_size: opts.max_body_size * 1024 * 1024,
+        wire_mapping: opts.wire_map.map(|filename| optimizer::load_wire_mapping_file(&filename).unwrap()),
+    };
+    let server = ProvingServer::bind(&opts.address, prover, r1cs, options).unwrap();
+    println!("Listening on http://{}", opts.address);
+    server.run();
+}
+
+fn info(opts: InfoOpts) {
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    let info = load_circuit_info_file(&circuit_file).unwrap();
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&info).unwrap());
+        return;
+    }
+    let optional = |v: Option<usize>| v.map_or("unknown".to_string(), |v| v.to_string());
+    println!("Circuit:              {}", circuit_file);
+    println!("Constraints:          {}", info.constraints);
+    println!("Wires:                {}", info.wires);
+    println!("  public outputs:     {}", optional(info.public_outputs));
+    println!("  public inputs:      {}", optional(info.public_inputs));
+    println!("  private inputs:     {}", optional(info.private_inputs));
+    println!("  public total:       {}", info.public);
+    println!("  private total:      {}", info.private);
+    println!("Non-zeros in A/B/C:   {} / {} / {}", info.nonzeros_a, info.nonzeros_b, info.nonzeros_c);
+    println!("snarkjs domain size:  {} (2^{})", info.domain_size, info.domain_bits);
+    println!("Params size:          ~{}", format_size(info.params_size));
+    println!("Prover memory:        ~{}", format_size(info.prover_memory));
+}
+
+fn optimize(opts: OptimizeOpts) {
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    println!("Loading circuit from {}...", circuit_file);
+    let (r1cs, header, labels) = if circuit_file.ends_with("json") {
+        let r1cs = r1cs_from_json_file::<Bn256>(&circuit_file);
+        let header = bn256_header(0, r1cs.num_inputs as u32 - 1, 0, 0, r1cs.num_variables as u64, 0);
+        let labels = (0..r1cs.num_variables as u64).collect::<Vec<_>>();
+        (r1cs, header, labels)
+    } else {
+        let file = r1cs_reader::read(BufReader::new(fs::File::open(&circuit_file).unwrap())).unwrap();
+        let num_inputs = (1 + file.header.n_pub_in + file.header.n_pub_out) as usize;
+        let num_variables = file.header.n_wires as usize;
+        let r1cs = R1CS {
+            num_inputs,
+            num_aux: num_variables - num_inputs,
+            num_variables,
+            constraints: file.constraints,
+        };
+        (r1cs, file.header, file.wire_mapping)
+    };
+
+    let (optimized, wire_map) = optimizer::optimize(&r1cs);
+    // private inputs follow public ones and are kept only if still used
+    let private_inputs = r1cs.num_inputs..r1cs.num_inputs + header.n_prv_in as usize;
+    let file = R1CSFile {
+        version: 1,
+        header: bn256_header(
+            optimized.num_variables as u32,
+            header.n_pub_out,
+            header.n_pub_in,
+            wire_map.iter().filter(|i| private_inputs.contains(i)).count() as u32,
+            header.n_labels,
+            optimized.constraints.len() as u32,
+        ),
+        constraints: optimized.constraints,
+        wire_mapping: wire_map.iter().map(|i| labels.get(*i).cloned().unwrap_or(*i as u64)).collect(),
+    };
+    r1cs_writer::write(BufWriter::new(fs::File::create(&opts.output).unwrap()), &file).unwrap();
+    fs::write(&opts.wire_map, serde_json::to_string(&wire_map).unwrap()).unwrap();
+    println!("Constraints: {} -> {}", r1cs.constraints.len(), file.constraints.len());
+    println!("Wires:       {} -> {}", r1cs.num_variables, optimized.num_variables);
+    println!("Saved {} and {}", opts.output, opts.wire_map);
+}
+
+fn lint(opts: LintOpts) {
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    let r1cs = load_r1cs(&circuit_file);
+    let names = load_wire_names(&circuit_file, opts.sym, r1cs.num_variables);
+    let findings = lint::lint(&r1cs, &names);
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&findings).unwrap());
+    } else {
+        for finding in findings.iter() {
+            println!("{}", finding);
+        }
+        println!("{} findings in {}", findings.len(), circuit_file);
+    }
+    if !findings.is_empty() {
+        std::process::exit(400);
+    }
+}
+
+fn fuzz_witness(opts: FuzzWitnessOpts) {
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    let witness_file = resolve_witness_file(opts.witness);
+    let r1cs = load_r1cs(&circuit_file);
+    let witness = load_witness::<Bn256>(&witness_file).unwrap();
+    let names = load_wire_names(&circuit_file, opts.sym, r1cs.num_variables);
+    let fuzzer = match WitnessFuzzer::new(&r1cs, &witness) {
+        Ok(fuzzer) => fuzzer,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(400);
+        }
+    };
+    println!("Mutating {} private wires...", r1cs.num_aux);
+    let hits = fuzzer.run(opts.iterations, &mut create_rng());
+    if hits.is_empty() {
+        println!("No alternative witnesses found after {} random mutations", opts.iterations);
+        return;
+    }
+    for hit in hits.iter() {
+        println!("Alternative witness found by mutating {}:", wire_name(&names, hit.mutated));
+        for wire in hit.diff.iter() {
+            println!("  {}: {} -> {}", wire_name(&names, *wire), repr_to_big(witness[*wire].into_repr()), repr_to_big(hit.witness[*wire].into_repr()));
+        }
+    }
+    println!("{} under-constrained wires found, proofs can be forged", hits.len());
+    std::process::exit(400);
+}
+
+fn diff(opts: DiffOpts) {
+    let (diff, old, new) = diff_r1cs_files(&opts.old, &opts.new).unwrap();
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&diff).unwrap());
+    } else {
+        let optional = |v: Option<u32>| v.map_or("unknown".to_string(), |v| v.to_string());
+        let changes = [
+            ("Wires", diff.old.wires.to_string(), diff.new.wires.to_string()),
+            ("Public outputs", optional(diff.old.public_outputs), optional(diff.new.public_outputs)),
+            ("Public inputs", optional(diff.old.public_inputs), optional(diff.new.public_inputs)),
+            ("Private inputs", optional(diff.old.private_inputs), optional(diff.new.private_inputs)),
+            ("Constraints", diff.old.constraints.to_string(), diff.new.constraints.to_string()),
+        ];
+        for (name, old, new) in changes.iter() {
+            if old == new {
+                println!("{:<22}{}", format!("{}:", name), old);
+            } else {
+                println!("{:<22}{} -> {}", format!("{}:", name), old, new);
+            }
+        }
+        let format_constraint = |(a, b, c): &Constraint<Bn256>| format!("({}) * ({}) = {}", format_lc::<Bn256>(a), format_lc::<Bn256>(b), format_lc::<Bn256>(c));
+        for i in diff.removed.iter() {
+            println!("- old #{}: {}", i, format_constraint(&old.constraints[*i]));
+        }
+        for i in diff.added.iter() {
+            println!("+ new #{}: {}", i, format_constraint(&new.constraints[*i]));
+        }
+        println!("Public interface:     {}", if diff.public_interface_unchanged { "unchanged" } else { "changed" });
+        if diff.structurally_identical {
+            println!("Circuits are structurally identical, existing params remain valid");
+        } else {
+            println!("Circuits differ, a new trusted setup is required");
+        }
+    }
+    if !diff.structurally_identical {
+        std::process::exit(400);
+    }
+}
+
+fn export_smt(opts: ExportSmtOpts) {
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    let r1cs = load_r1cs(&circuit_file);
+    let public = opts.public.map(|wires| wires.split(',').map(|w| match w.trim().parse() {
+        Ok(wire) => wire,
+        Err(_) => {
+            println!("Invalid wire index {:?} in --public, expected comma separated wire indices", w.trim());
+            std::process::exit(400);
+        }
+    }).collect());
+    let options = SmtOptions {
+        encoding: if opts.encoding == "int" { SmtEncoding::ModularInteger } else { SmtEncoding::FiniteField },
+        public,
+        uniqueness: opts.uniqueness,
+    };
+    let writer = BufWriter::new(fs::File::create(&opts.output).unwrap());
+    if let Err(e) = write_smt(writer, &r1cs, &options) {
+        println!("{}", e);
+        std::process::exit(400);
+    }
+    println!("Created {}", opts.output);
+}
+
+fn graph(opts: GraphOpts) {
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    let r1cs = load_r1cs(&circuit_file);
+    let names = load_wire_names(&circuit_file, opts.sym, r1cs.num_variables);
+    let radius = opts.radius;
+    let around = opts.around.map(|wire| {
+        let index = wire.parse::<usize>().ok()
+            .or_else(|| names.iter().position(|name| name.as_deref() == Some(wire.as_str())));
+        match index {
+            Some(index) if index < r1cs.num_variables => (index, radius),
+            _ => {
+                println!("Unknown wire {}", wire);
+                std::process::exit(400);
+            }
+        }
+    });
+    let (format, extension) = if opts.format == "graphml" { (GraphFormat::GraphML, "graphml") } else { (GraphFormat::Dot, "dot") };
+    let output = opts.output.unwrap_or_else(|| format!("circuit.{}", extension));
+    let graph = build_graph(&r1cs, &names, &GraphOptions { collapse: opts.collapse, around });
+    write_graph(BufWriter::new(fs::File::create(&output).unwrap()), &graph, format).unwrap();
+    println!("Created {} with {} nodes and {} edges", output, graph.nodes.len(), graph.edges.len());
+}
+
+fn solve(opts: SolveOpts) {
+    let circuit_file = resolve_circuit_file(opts.circuit);
+    let r1cs = load_r1cs(&circuit_file);
+    let names = load_wire_names(&circuit_file, opts.sym, r1cs.num_variables);
+    let input: serde_json::Value = serde_json::from_str(&fs::read_to_string(&opts.input).unwrap()).unwrap();
+    let inputs = match inputs_from_json::<Bn256>(&input, &names) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(400);
+        }
+    };
+    let witness = match solver::solve(&r1cs, &inputs) {
+        Ok(witness) => witness,
+        Err(e) => {
+            println!("{}", e);
+            if let SolveError::Unsolved(wires) = e {
+                for wire in wires.iter().take(20) {
+                    println!("  {}", wire_name(&names, *wire));
+                }
+                if wires.len() > 20 {
+                    println!("  ...and {} more", wires.len() - 20);
+                }
+            }
+            std::process::exit(400);
+        }
+    };
+    if opts.witness.ends_with("json") {
+        let values = witness.iter().map(|x| repr_to_big(x.into_repr())).collect::<Vec<_>>();
+        fs::write(&opts.witness, serde_json::to_string(&values).unwrap()).unwrap();
+    } else {
+        wtns_writer::write(BufWriter::new(fs::File::create(&opts.witness).unwrap()), &witness).unwrap();
+    }
+    println!("Saved {} with {} wires", opts.witness, witness.len());
+}
+
+/// Sym entries and whether the witness is indexed by signal label rather than wire.
+/// Witnesses of older circom versions contain all signals, one per label of the .r1cs file.
+fn load_witness_symbols(circuit: Option<String>, sym: Option<String>, witness_len: usize) -> (Option<Vec<SymEntry>>, bool) {
+    let circuit_file = resolve_circuit_file(circuit);
+    let entries = resolve_sym_file(&circuit_file, sym).map(|filename| sym_reader::read_file(&filename).unwrap());
+    // only the header is needed, json circuits don't have labels
+    let layout = match circuit_file.ends_with("json") {
+        true => None,
+        false => fs::File::open(&circuit_file).ok().and_then(|file| r1cs_reader::read_layout(BufReader::new(file)).ok()),
+    };
+    let by_label = layout.is_some_and(|layout| witness_len != layout.header.n_wires as usize && witness_len as u64 == layout.header.n_labels);
+    (entries, by_label)
+}
+
+fn witness_show(opts: WitnessShowOpts) {
+    let witness_file = resolve_witness_file(opts.witness);
+    let witness = load_witness::<Bn256>(&witness_file).unwrap();
+    let (entries, by_label) = load_witness_symbols(opts.circuit, opts.sym, witness.len());
+    if entries.is_none() && opts.filter.is_some() {
+        eprintln!("No sym file found, matching the filter against wire indices");
+    }
+    let values = witness_entries::<Bn256>(&witness, entries.as_deref(), by_label, opts.filter.as_deref());
+    for entry in values.iter() {
+        let value = format_value::<Bn256>(&entry.value, opts.hex);
+        match &entry.name {
+            Some(name) => println!("{:<8} {} = {}", entry.index, name, value),
+            None => println!("{:<8} {}", entry.index, value),
+        }
+    }
+}
+
+fn witness_diff(opts: WitnessDiffOpts) {
+    let a = load_witness::<Bn256>(&opts.a).unwrap();
+    let b = load_witness::<Bn256>(&opts.b).unwrap();
+    let (entries, by_label) = load_witness_symbols(opts.circuit, opts.sym, a.len());
+    let names = entries.map_or(vec![], |entries| index_names(&entries, by_label, a.len().max(b.len())));
+    let diff = diff_witnesses::<Bn256>(&a, &b);
+    let hex = opts.hex;
+    let format = |value: Option<&Fr>| value.map_or("missing".to_string(), |v| format_value::<Bn256>(v, hex));
+    for index in diff.iter() {
+        match names.get(*index).cloned().flatten() {
+            Some(name) => println!("{:<8} {}: {} -> {}", index, name, format(a.get(*index)), format(b.get(*index))),
+            None => println!("{:<8} {} -> {}", index, format(a.get(*index)), format(b.get(*index))),
+        }
+    }
+    if diff.is_empty() {
+        println!("Witnesses are identical");
+    } else {
+        println!("{} of {} values differ", diff.len(), a.len().max(b.len()));
+        std::process::exit(400);
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
@@ -0,0 +1,321 @@
+//! Node.js bindings, enabled with the `node` feature.
+//!
+//! Loading, proving and verifying run on the libuv thread pool and return promises:
+//!
+//! ```js
+//! const zkutil = require('./zkutil.node');
+//! const params = await zkutil.loadParams('params.bin');
+//! const circuit = await zkutil.loadR1cs('circuit.r1cs');
+//! const witness = await zkutil.witnessFromBin(fs.readFileSync('witness.wtns'));
+//! const proof = await zkutil.prove(params, circuit, witness);
+//! console.log(zkutil.proofToJson(proof), proof.publicSignals);
+//! console.log(await zkutil.verify(params, proof, proof.publicSignals));
+//! ```
+extern crate bellman_ce;
+
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use napi::{Env, Error, Status, Task};
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi_derive::napi;
+use bellman_ce::{
+    SynthesisError,
+    groth16::Proof,
+    pairing::{
+        ff::PrimeField,
+        bn256::{Bn256, Fr},
+    },
+};
+
+use crate::circom_circuit::{
+    self,
+    CircomCircuit,
+    CircomCircuitRef,
+    ParamsHeader,
+    R1CS,
+    create_rng,
+    generate_random_parameters,
+    parse_proof_json,
+    r1cs_from_bin_file,
+    try_r1cs_from_json,
+    try_r1cs_from_json_file,
+};
+use crate::prover::{Prover, load_prover_file};
+use crate::utils::{repr_to_big, synthesis_error_message};
+use crate::verifier::Verifier;
+
+fn io_error(e: io::Error) -> Error {
+    let status = match e.kind() {
+        io::ErrorKind::InvalidData => Status::InvalidArg,
+        _ => Status::GenericFailure,
+    };
+    Error::new(status, e.to_string())
+}
+
+fn synthesis_error(e: SynthesisError) -> Error {
+    Error::from_reason(synthesis_error_message(&e))
+}
+
+fn to_fields(values: &[String]) -> napi::Result<Vec<Fr>> {
+    values.iter()
+        .map(|x| Fr::from_str(x).ok_or_else(|| Error::new(Status::InvalidArg, format!("Invalid field element {}", x))))
+        .collect()
+}
+
+fn from_fields(values: &[Fr]) -> Vec<String> {
+    values.iter().map(|x| repr_to_big(x.into_repr())).collect()
+}
+
+/// Trusted setup parameters
+#[napi]
+pub struct Params {
+    prover: Arc<Prover<Bn256>>,
+    /// Prepared once, so that verifications don't repeat the pairing precomputation
+    verifier: Arc<Verifier<Bn256>>,
+}
+
+impl Params {
+    fn new(prover: Prover<Bn256>) -> Params {
+        Params { verifier: Arc::new(prover.verifier()), prover: Arc::new(prover) }
+    }
+}
+
+/// Circuit constraints
+#[napi(js_name = "R1CS")]
+pub struct R1cs {
+    r1cs: Arc<R1CS<Bn256>>,
+}
+
+#[napi]
+impl R1cs {
+    /// Parses circuit JSON in circom format
+    #[napi(factory)]
+    pub fn from_json(data: String) -> napi::Result<R1cs> {
+        Ok(R1cs { r1cs: Arc::new(try_r1cs_from_json(data.as_bytes()).map_err(io_error)?) })
+    }
+
+    #[napi(getter)]
+    pub fn num_inputs(&self) -> u32 {
+        self.r1cs.num_inputs as u32
+    }
+
+    #[napi(getter)]
+    pub fn num_variables(&self) -> u32 {
+        self.r1cs.num_variables as u32
+    }
+
+    #[napi(getter)]
+    pub fn num_constraints(&self) -> u32 {
+        self.r1cs.constraints.len() as u32
+    }
+}
+
+/// Groth16 proof along with its public inputs
+#[napi(js_name = "Proof")]
+pub struct JsProof {
+    proof: Proof<Bn256>,
+    public: Vec<Fr>,
+}
+
+#[napi]
+impl JsProof {
+    /// Parses proof JSON in snarkjs format, public inputs are given separately
+    #[napi(factory)]
+    pub fn from_json(data: String, public_signals: Vec<String>) -> napi::Result<JsProof> {
+        Ok(JsProof {
+            proof: parse_proof_json(&data).map_err(io_error)?,
+            public: to_fields(&public_signals)?,
+        })
+    }
+
+    #[napi(getter)]
+    pub fn public_signals(&self) -> Vec<String> {
+        from_fields(&self.public)
+    }
+}
+
+pub struct LoadParamsTask {
+    path: String,
+}
+
+impl Task for LoadParamsTask {
+    type Output = Params;
+    type JsValue = Params;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        Ok(Params::new(load_prover_file(&self.path).map_err(io_error)?))
+    }
+
+    fn resolve(&mut self, _env: Env, params: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(params)
+    }
+}
+
+pub struct SetupTask {
+    r1cs: Arc<R1CS<Bn256>>,
+}
+
+impl Task for SetupTask {
+    type Output = Params;
+    type JsValue = Params;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let header = ParamsHeader { filtered: false, circuit_hash: Some(self.r1cs.hash()) };
+        let circuit = CircomCircuit {
+            r1cs: (*self.r1cs).clone(),
+            witness: None,
+            wire_mapping: None,
+        };
+        let params = generate_random_parameters(circuit, create_rng()).map_err(synthesis_error)?;
+        Ok(Params::new(Prover::with_header(params, header)))
+    }
+
+    fn resolve(&mut self, _env: Env, params: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(params)
+    }
+}
+
+pub struct LoadR1csTask {
+    path: String,
+}
+
+impl Task for LoadR1csTask {
+    type Output = R1CS<Bn256>;
+    type JsValue = R1cs;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        if self.path.ends_with("json") {
+            try_r1cs_from_json_file(&self.path).map_err(io_error)
+        } else {
+            Ok(r1cs_from_bin_file(&self.path).map_err(io_error)?.0)
+        }
+    }
+
+    fn resolve(&mut self, _env: Env, r1cs: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(R1cs { r1cs: Arc::new(r1cs) })
+    }
+}
+
+pub struct WitnessTask {
+    data: Buffer,
+}
+
+impl Task for WitnessTask {
+    type Output = Vec<Fr>;
+    type JsValue = Vec<String>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        circom_circuit::witness_from_bin::<Bn256, _>(&self.data[..]).map_err(io_error)
+    }
+
+    fn resolve(&mut self, _env: Env, witness: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(from_fields(&witness))
+    }
+}
+
+pub struct ProveTask {
+    prover: Arc<Prover<Bn256>>,
+    r1cs: Arc<R1CS<Bn256>>,
+    witness: Vec<Fr>,
+}
+
+impl Task for ProveTask {
+    type Output = JsProof;
+    type JsValue = JsProof;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        if self.witness.len() != self.r1cs.num_variables {
+            return Err(Error::new(Status::InvalidArg, format!("Witness has {} values, circuit has {} wires", self.witness.len(), self.r1cs.num_variables)));
+        }
+        let circuit = CircomCircuitRef {
+            r1cs: &self.r1cs,
+            witness: Some(&self.witness),
+            wire_mapping: None,
+        };
+        // a panic on a libuv worker would take the whole node process down
+        let proof = panic::catch_unwind(AssertUnwindSafe(|| self.prover.prove(circuit, create_rng())))
+            .map_err(|_| Error::from_reason("Prover panicked"))?
+            .map_err(synthesis_error)?;
+        Ok(JsProof { proof, public: circuit.get_public_inputs().unwrap() })
+    }
+
+    fn resolve(&mut self, _env: Env, proof: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(proof)
+    }
+}
+
+pub struct VerifyTask {
+    verifier: Arc<Verifier<Bn256>>,
+    proof: Proof<Bn256>,
+    inputs: Vec<Fr>,
+}
+
+impl Task for VerifyTask {
+    type Output = bool;
+    type JsValue = bool;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        self.verifier.verify(&self.proof, &self.inputs).map_err(synthesis_error)
+    }
+
+    fn resolve(&mut self, _env: Env, valid: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(valid)
+    }
+}
+
+/// Loads params.bin
+#[napi]
+pub fn load_params(path: String) -> AsyncTask<LoadParamsTask> {
+    AsyncTask::new(LoadParamsTask { path })
+}
+
+/// Generates random parameters for the circuit, not suitable for production use
+#[napi(ts_return_type = "Promise<Params>")]
+pub fn setup(circuit: &R1cs) -> AsyncTask<SetupTask> {
+    AsyncTask::new(SetupTask { r1cs: circuit.r1cs.clone() })
+}
+
+/// Loads circuit from circom .r1cs or .json file
+#[napi(js_name = "loadR1cs")]
+pub fn load_r1cs(path: String) -> AsyncTask<LoadR1csTask> {
+    AsyncTask::new(LoadR1csTask { path })
+}
+
+/// Parses .wtns file contents into a list of decimal strings
+#[napi]
+pub fn witness_from_bin(data: Buffer) -> AsyncTask<WitnessTask> {
+    AsyncTask::new(WitnessTask { data })
+}
+
+/// Generates a proof for the witness given as a list of decimal strings
+#[napi(ts_return_type = "Promise<Proof>")]
+pub fn prove(params: &Params, circuit: &R1cs, witness: Vec<String>) -> napi::Result<AsyncTask<ProveTask>> {
+    params.prover.check_circuit(&circuit.r1cs).map_err(io_error)?;
+    Ok(AsyncTask::new(ProveTask {
+        prover: params.prover.clone(),
+        r1cs: circuit.r1cs.clone(),
+        witness: to_fields(&witness)?,
+    }))
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn verify(params: &Params, proof: &JsProof, public_signals: Vec<String>) -> napi::Result<AsyncTask<VerifyTask>> {
+    Ok(AsyncTask::new(VerifyTask {
+        verifier: params.verifier.clone(),
+        proof: proof.proof.clone(),
+        inputs: to_fields(&public_signals)?,
+    }))
+}
+
+/// Proof in snarkjs compatible JSON format
+#[napi]
+pub fn proof_to_json(proof: &JsProof) -> napi::Result<String> {
+    circom_circuit::proof_to_json(&proof.proof).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Solidity verifier contract source
+#[napi]
+pub fn create_verifier_sol(params: &Params) -> String {
+    circom_circuit::create_verifier_sol(params.prover.params())
+}
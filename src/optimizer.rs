@@ -0,0 +1,305 @@
+//! Constraint system optimizer, mostly useful for circuits compiled by older
+//! circom versions that emit lots of linear and repeated constraints.
+//!
+//! The optimized circuit keeps wire 0 and public inputs at the same indices, so
+//! public inputs and proofs are interchangeable with the original circuit.
+//! Remaining private wires are renumbered, `optimize` returns a mapping from new
+//! wire indices to the original ones that can be used with `remap_witness`.
+extern crate bellman_ce;
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use itertools::Itertools;
+use bellman_ce::pairing::{
+    Engine,
+    ff::{Field, PrimeField, ScalarEngine},
+};
+
+use crate::circom_circuit::{Constraint, R1CS};
+
+type Lc<E> = BTreeMap<usize, <E as ScalarEngine>::Fr>;
+
+fn add_term<E: Engine>(lc: &mut Lc<E>, index: usize, coeff: &E::Fr) {
+    let entry = lc.entry(index).or_insert_with(E::Fr::zero);
+    entry.add_assign(coeff);
+    if entry.is_zero() {
+        lc.remove(&index);
+    }
+}
+
+fn add_scaled<E: Engine>(lc: &mut Lc<E>, other: &Lc<E>, scale: &E::Fr) {
+    for (index, coeff) in other.iter() {
+        let mut term = *coeff;
+        term.mul_assign(scale);
+        add_term::<E>(lc, *index, &term);
+    }
+}
+
+/// Merges duplicate terms and drops zero ones
+fn to_lc<E: Engine>(terms: &[(usize, E::Fr)]) -> Lc<E> {
+    let mut lc = Lc::<E>::new();
+    for (index, coeff) in terms.iter() {
+        add_term::<E>(&mut lc, *index, coeff);
+    }
+    lc
+}
+
+/// Returns the value of a linear combination that only depends on wire 0
+fn constant<E: Engine>(lc: &Lc<E>) -> Option<E::Fr> {
+    match lc.len() {
+        0 => Some(E::Fr::zero()),
+        1 => lc.get(&0).cloned(),
+        _ => None,
+    }
+}
+
+/// If the constraint is `k * B = C` or `A * k = C`, returns `k * B - C` or `k * A - C` that must be zero
+fn linear_form<E: Engine>(a: &Lc<E>, b: &Lc<E>, c: &Lc<E>) -> Option<Lc<E>> {
+    let (k, other) = match (constant::<E>(a), constant::<E>(b)) {
+        (Some(k), _) => (k, b),
+        (_, Some(k)) => (k, a),
+        _ => return None,
+    };
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    let mut lc = Lc::<E>::new();
+    add_scaled::<E>(&mut lc, other, &k);
+    add_scaled::<E>(&mut lc, c, &minus_one);
+    Some(lc)
+}
+
+fn lc_key<E: Engine>(lc: &Lc<E>) -> Vec<u64> {
+    let mut key = Vec::with_capacity(lc.len() * 5);
+    for (index, coeff) in lc.iter() {
+        key.push(*index as u64);
+        key.extend_from_slice(coeff.into_repr().as_ref());
+    }
+    key
+}
+
+/// Key that is the same for constraints that only differ in order or
+/// duplicates of terms and order of A and B
+pub fn constraint_key<E: Engine>(constraint: &Constraint<E>) -> (Vec<u64>, Vec<u64>, Vec<u64>) {
+    let a = lc_key::<E>(&to_lc::<E>(&constraint.0));
+    let b = lc_key::<E>(&to_lc::<E>(&constraint.1));
+    let c = lc_key::<E>(&to_lc::<E>(&constraint.2));
+    if a <= b { (a, b, c) } else { (b, a, c) }
+}
+
+/// Eliminated wires expressed via the remaining ones
+struct Substitutions<E: Engine> {
+    exprs: HashMap<usize, Lc<E>>,
+    /// wire -> eliminated wires whose expressions might reference it
+    users: HashMap<usize, Vec<usize>>,
+}
+
+impl<E: Engine> Substitutions<E> {
+    fn new() -> Self {
+        Substitutions { exprs: HashMap::new(), users: HashMap::new() }
+    }
+
+    fn apply(&self, lc: &Lc<E>) -> Lc<E> {
+        let mut result = Lc::<E>::new();
+        for (index, coeff) in lc.iter() {
+            match self.exprs.get(index) {
+                Some(expr) => add_scaled::<E>(&mut result, expr, coeff),
+                None => add_term::<E>(&mut result, *index, coeff),
+            }
+        }
+        result
+    }
+
+    /// Adds `wire = expr`, where expr doesn't reference already eliminated wires
+    fn eliminate(&mut self, wire: usize, expr: Lc<E>) {
+        for user in self.users.remove(&wire).unwrap_or_default() {
+            let user_expr = self.exprs.get_mut(&user).unwrap();
+            if let Some(coeff) = user_expr.remove(&wire) {
+                add_scaled::<E>(user_expr, &expr, &coeff);
+                for index in expr.keys() {
+                    self.users.entry(*index).or_default().push(user);
+                }
+            }
+        }
+        for index in expr.keys() {
+            self.users.entry(*index).or_default().push(wire);
+        }
+        self.exprs.insert(wire, expr);
+    }
+}
+
+/// Optimizes the constraint system:
+/// * merges duplicate terms in linear combinations
+/// * eliminates linear constraints by substituting one of their private wires
+/// * removes duplicate and trivially satisfied constraints
+/// * removes private wires that aren't used in any constraint
+///
+/// Returns the optimized R1CS and mapping from its wires to the wires of the original one.
+pub fn optimize<E: Engine>(r1cs: &R1CS<E>) -> (R1CS<E>, Vec<usize>) {
+    let num_inputs = r1cs.num_inputs;
+    let mut subs = Substitutions::<E>::new();
+    let mut public_linear = vec![];
+    let mut constraints = r1cs.constraints.iter()
+        .map(|(a, b, c)| Some((to_lc::<E>(a), to_lc::<E>(b), to_lc::<E>(c))))
+        .collect_vec();
+    // wire -> constraints that reference it, only those change when the wire is eliminated
+    let mut wire_constraints = HashMap::<usize, Vec<usize>>::new();
+    for (i, (a, b, c)) in constraints.iter().flatten().enumerate() {
+        for index in a.keys().chain(b.keys()).chain(c.keys()) {
+            wire_constraints.entry(*index).or_default().push(i);
+        }
+    }
+
+    // substitutions can make other constraints linear, those are checked again
+    let mut pending = (0..constraints.len()).collect::<VecDeque<_>>();
+    let mut is_pending = vec![true; constraints.len()];
+    while let Some(i) = pending.pop_front() {
+        is_pending[i] = false;
+        let (old_a, old_b, old_c) = constraints[i].take().unwrap();
+        let (a, b, c) = (subs.apply(&old_a), subs.apply(&old_b), subs.apply(&old_c));
+        let mut lc = match linear_form::<E>(&a, &b, &c) {
+            Some(lc) => lc,
+            None => {
+                for index in a.keys().chain(b.keys()).chain(c.keys()) {
+                    if !old_a.contains_key(index) && !old_b.contains_key(index) && !old_c.contains_key(index) {
+                        wire_constraints.entry(*index).or_default().push(i);
+                    }
+                }
+                constraints[i] = Some((a, b, c));
+                continue;
+            }
+        };
+        // public inputs have to stay, so only private wires are eliminated
+        match lc.keys().rev().find(|index| **index >= num_inputs).cloned() {
+            Some(wire) => {
+                let mut scale = lc.remove(&wire).unwrap().inverse().unwrap();
+                scale.negate();
+                let mut expr = Lc::<E>::new();
+                add_scaled::<E>(&mut expr, &lc, &scale);
+                subs.eliminate(wire, expr);
+                for j in wire_constraints.remove(&wire).unwrap_or_default() {
+                    if constraints[j].is_some() && !is_pending[j] {
+                        is_pending[j] = true;
+                        pending.push_back(j);
+                    }
+                }
+            }
+            None => if !lc.is_empty() {
+                public_linear.push(lc);
+            },
+        }
+    }
+    let constraints = constraints.into_iter().flatten().collect_vec();
+
+    // linear constraints on public inputs are kept as `lc * 1 = 0`
+    let one = [(0, E::Fr::one())].iter().cloned().collect::<Lc<E>>();
+    let mut seen = HashSet::new();
+    let mut result = vec![];
+    for (a, b, c) in public_linear.into_iter().map(|lc| (lc, one.clone(), Lc::<E>::new())).chain(constraints) {
+        // A * B = C is the same constraint as B * A = C
+        let (a, b) = if lc_key::<E>(&a) <= lc_key::<E>(&b) { (a, b) } else { (b, a) };
+        if seen.insert((lc_key::<E>(&a), lc_key::<E>(&b), lc_key::<E>(&c))) {
+            result.push((a, b, c));
+        }
+    }
+
+    let mut used = (0..r1cs.num_variables).map(|index| index < num_inputs).collect_vec();
+    for (a, b, c) in result.iter() {
+        for index in a.keys().chain(b.keys()).chain(c.keys()) {
+            used[*index] = true;
+        }
+    }
+    let wire_mapping = (0..r1cs.num_variables).filter(|index| used[*index]).collect_vec();
+    let mut new_index = vec![0; r1cs.num_variables];
+    for (new, old) in wire_mapping.iter().enumerate() {
+        new_index[*old] = new;
+    }
+
+    let remap = |lc: Lc<E>| lc.into_iter().map(|(index, coeff)| (new_index[index], coeff)).collect_vec();
+    let constraints: Vec<Constraint<E>> = result.into_iter()
+        .map(|(a, b, c)| (remap(a), remap(b), remap(c)))
+        .collect();
+    let optimized = R1CS {
+        num_inputs,
+        num_aux: wire_mapping.len() - num_inputs,
+        num_variables: wire_mapping.len(),
+        constraints,
+    };
+    (optimized, wire_mapping)
+}
+
+/// Converts a witness of the original circuit to a witness of the optimized one
+pub fn remap_witness<F: Copy>(witness: &[F], wire_mapping: &[usize]) -> Vec<F> {
+    wire_mapping.iter().map(|index| witness[*index]).collect()
+}
+
+/// Same as `remap_witness`, returns an error instead of panicking if the witness is too short
+pub fn try_remap_witness<F: Copy>(witness: &[F], wire_mapping: &[usize]) -> io::Result<Vec<F>> {
+    match wire_mapping.iter().find(|index| **index >= witness.len()) {
+        Some(index) => Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Witness has {} values, wire map references wire {}", witness.len(), index,
+        ))),
+        None => Ok(remap_witness(witness, wire_mapping)),
+    }
+}
+
+/// Loads wire mapping saved by `zkutil optimize`
+pub fn load_wire_mapping_file(filename: &str) -> io::Result<Vec<usize>> {
+    Ok(serde_json::from_str(&fs::read_to_string(filename)?)?)
+}
+
+#[test]
+fn optimize_linear_and_duplicates() {
+    use bellman_ce::pairing::bn256::{Bn256, Fr};
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+
+    // wires: 1, out, x, t, unused; t = x + 1, out = t * t
+    let r1cs = R1CS::<Bn256> {
+        num_inputs: 2,
+        num_aux: 3,
+        num_variables: 5,
+        constraints: vec![
+            (vec![(0, fr("1"))], vec![(2, fr("1")), (0, fr("1")), (2, fr("0"))], vec![(3, fr("1"))]),
+            (vec![(3, fr("1"))], vec![(3, fr("1"))], vec![(1, fr("1"))]),
+            (vec![(3, fr("1"))], vec![(3, fr("1"))], vec![(1, fr("1"))]),
+        ],
+    };
+    let witness = vec![fr("1"), fr("16"), fr("3"), fr("4"), fr("0")];
+    assert_eq!(r1cs.first_unsatisfied(&witness), None);
+
+    let (optimized, wire_mapping) = optimize(&r1cs);
+    assert_eq!(wire_mapping, vec![0, 1, 2]);
+    assert_eq!((optimized.num_inputs, optimized.num_aux), (2, 1));
+    assert_eq!(optimized.constraints.len(), 1);
+    assert_eq!(optimized.first_unsatisfied(&remap_witness(&witness, &wire_mapping)), None);
+    assert_eq!(optimized.first_unsatisfied(&[fr("1"), fr("9"), fr("3")]), Some(0));
+}
+
+#[test]
+fn optimize_substitution_chain() {
+    use bellman_ce::pairing::bn256::{Bn256, Fr};
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+
+    // wires: 1, out, x, a, b; out = b * b, b = a + 1, a = x + 2, the first constraint is rechecked after each elimination
+    let r1cs = R1CS::<Bn256> {
+        num_inputs: 2,
+        num_aux: 3,
+        num_variables: 5,
+        constraints: vec![
+            (vec![(4, fr("1"))], vec![(4, fr("1"))], vec![(1, fr("1"))]),
+            (vec![(0, fr("1"))], vec![(3, fr("1")), (0, fr("1"))], vec![(4, fr("1"))]),
+            (vec![(0, fr("1"))], vec![(2, fr("1")), (0, fr("2"))], vec![(3, fr("1"))]),
+        ],
+    };
+    let witness = vec![fr("1"), fr("36"), fr("3"), fr("5"), fr("6")];
+    assert_eq!(r1cs.first_unsatisfied(&witness), None);
+
+    let (optimized, wire_mapping) = optimize(&r1cs);
+    assert_eq!(wire_mapping, vec![0, 1, 2]);
+    assert_eq!(optimized.constraints.len(), 1);
+    assert_eq!(optimized.first_unsatisfied(&try_remap_witness(&witness, &wire_mapping).unwrap()), None);
+    assert_eq!(
+        try_remap_witness(&witness[..2], &wire_mapping).unwrap_err().to_string(),
+        "Witness has 2 values, wire map references wire 2",
+    );
+}
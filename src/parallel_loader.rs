@@ -0,0 +1,223 @@
+//! Multi-threaded loading of .r1cs and params files. Bytes are read sequentially
+//! in batches, and field elements and curve points of each batch are decoded on
+//! all threads. Results and errors are the same as with the sequential readers.
+extern crate bellman_ce;
+
+use std::fs::OpenOptions;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::thread;
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use bellman_ce::{
+    groth16::{Parameters, VerifyingKey},
+    pairing::{
+        CurveAffine,
+        EncodedPoint,
+        bn256::Bn256,
+    },
+};
+
+use crate::circom_circuit::{Constraint, ParamsHeader, R1CS, read_params_header, try_r1cs_from_json_file};
+use crate::circuit_info::r1cs_from_file;
+use crate::r1cs_reader::{self, Header, Layout, R1CSFile, read_constraint};
+
+/// Called with the section name, number of items decoded so far and total
+pub type Progress<'a> = &'a (dyn Fn(&str, usize, usize) + Sync);
+
+/// Rows or points decoded per batch on every thread
+const BATCH_PER_THREAD: usize = 1 << 14;
+
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Decodes items `0..count` on `threads` threads, keeping the order. The error of the
+/// first failed item is returned, same as when decoding one by one.
+fn decode_parallel<T: Send, F: Fn(usize) -> io::Result<T> + Sync>(count: usize, threads: usize, decode: F) -> io::Result<Vec<T>> {
+    let chunk = count.div_ceil(threads.max(1)).max(1);
+    let decode = &decode;
+    let chunks = thread::scope(|scope| {
+        let handles = (0..count).step_by(chunk)
+            .map(|start| scope.spawn(move || (start..count.min(start + chunk)).map(decode).collect::<io::Result<Vec<T>>>()))
+            .collect::<Vec<_>>();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+    });
+    let mut result = Vec::with_capacity(count);
+    for chunk in chunks {
+        result.extend(chunk?);
+    }
+    Ok(result)
+}
+
+fn read_constraints<R: Read>(mut reader: R, header: &Header, threads: usize, progress: Progress) -> io::Result<Vec<Constraint<Bn256>>> {
+    let total = header.n_constraints as usize;
+    let mut constraints = Vec::with_capacity(total);
+    progress("constraints", 0, total);
+    while constraints.len() < total {
+        let batch = (total - constraints.len()).min(BATCH_PER_THREAD * threads);
+        // raw bytes and the offset of every row, only term counts are parsed here
+        let mut bytes = vec![];
+        let mut rows = Vec::with_capacity(batch);
+        for _ in 0..batch {
+            rows.push(bytes.len());
+            for _ in 0..3 {
+                let n_terms = reader.read_u32::<LittleEndian>()?;
+                bytes.extend_from_slice(&n_terms.to_le_bytes());
+                let start = bytes.len();
+                bytes.resize(start + n_terms as usize * (4 + header.field_size as usize), 0);
+                reader.read_exact(&mut bytes[start..])?;
+            }
+        }
+        let bytes = &bytes;
+        constraints.extend(decode_parallel(batch, threads, |i| read_constraint::<_, Bn256>(&bytes[rows[i]..], header))?);
+        progress("constraints", constraints.len(), total);
+    }
+    Ok(constraints)
+}
+
+/// Parallel version of `r1cs_reader::read`
+pub fn read_r1cs<R: Read + Seek>(mut reader: R, threads: usize, progress: Progress) -> io::Result<R1CSFile<Bn256>> {
+    let Layout { version, header, constraints, wire2label } = r1cs_reader::read_layout(&mut reader)?;
+
+    reader.seek(SeekFrom::Start(constraints.offset))?;
+    let constraints = read_constraints(&mut reader, &header, threads, progress)?;
+
+    reader.seek(SeekFrom::Start(wire2label.offset))?;
+    if wire2label.size != header.n_wires as u64 * 8 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid map section size"));
+    }
+    let mut wire_mapping = vec![0u64; header.n_wires as usize];
+    reader.read_u64_into::<LittleEndian>(&mut wire_mapping)?;
+    if wire_mapping[0] != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Wire 0 should always be mapped to 0"));
+    }
+
+    Ok(R1CSFile { version, header, constraints, wire_mapping })
+}
+
+pub fn read_r1cs_file(filename: &str, threads: usize, progress: Progress) -> io::Result<R1CSFile<Bn256>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    read_r1cs(BufReader::new(reader), threads, progress)
+}
+
+fn decode_point<G: CurveAffine>(bytes: &[u8], checked: bool) -> io::Result<G> {
+    let mut repr = G::Uncompressed::empty();
+    repr.as_mut().copy_from_slice(bytes);
+    if checked {
+        repr.into_affine()
+    } else {
+        repr.into_affine_unchecked()
+    }
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    .and_then(|e| if e.is_zero() {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "point at infinity"))
+    } else {
+        Ok(e)
+    })
+}
+
+fn read_points<G: CurveAffine, R: Read>(mut reader: R, name: &str, checked: bool, threads: usize, progress: Progress) -> io::Result<Vec<G>> {
+    let total = reader.read_u32::<BigEndian>()? as usize;
+    let size = G::Uncompressed::size();
+    let mut points = Vec::with_capacity(total);
+    progress(name, 0, total);
+    while points.len() < total {
+        let batch = (total - points.len()).min(BATCH_PER_THREAD * threads);
+        let mut bytes = vec![0u8; batch * size];
+        reader.read_exact(&mut bytes)?;
+        let bytes = &bytes;
+        points.extend(decode_parallel(batch, threads, |i| decode_point::<G>(&bytes[i * size..(i + 1) * size], checked))?);
+        progress(name, points.len(), total);
+    }
+    Ok(points)
+}
+
+/// Parallel version of `Parameters::read`
+pub fn read_params<R: Read>(mut reader: R, checked: bool, threads: usize, progress: Progress) -> io::Result<Parameters<Bn256>> {
+    let vk = VerifyingKey::<Bn256>::read(&mut reader)?;
+    let h = read_points(&mut reader, "h", checked, threads, progress)?;
+    let l = read_points(&mut reader, "l", checked, threads, progress)?;
+    let a = read_points(&mut reader, "a", checked, threads, progress)?;
+    let b_g1 = read_points(&mut reader, "b_g1", checked, threads, progress)?;
+    let b_g2 = read_points(&mut reader, "b_g2", checked, threads, progress)?;
+    Ok(Parameters {
+        vk,
+        h: Arc::new(h),
+        l: Arc::new(l),
+        a: Arc::new(a),
+        b_g1: Arc::new(b_g1),
+        b_g2: Arc::new(b_g2),
+    })
+}
+
+/// Parallel version of `load_params_with_header`
+pub fn load_params_with_header<R: Read>(mut reader: R, threads: usize, progress: Progress) -> io::Result<(ParamsHeader, Parameters<Bn256>)> {
+    let (header, prefix) = read_params_header(&mut reader)?;
+    Ok((header, read_params((&prefix[..]).chain(reader), true, threads, progress)?))
+}
+
+pub fn load_params_file_with_header(filename: &str, threads: usize, progress: Progress) -> io::Result<(ParamsHeader, Parameters<Bn256>)> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    load_params_with_header(BufReader::new(reader), threads, progress)
+}
+
+/// Parallel version of `circuit_info::load_r1cs_file_with_header`, .json circuits are loaded as usual
+pub fn load_r1cs_file_with_header(filename: &str, threads: usize, progress: Progress) -> io::Result<(R1CS<Bn256>, Option<Header>)> {
+    if filename.ends_with("json") {
+        return Ok((try_r1cs_from_json_file(filename)?, None));
+    }
+    let (r1cs, header) = r1cs_from_file(read_r1cs_file(filename, threads, progress)?);
+    Ok((r1cs, Some(header)))
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    use std::io::Cursor;
+    use crate::circom_circuit::write_params;
+
+    let no_progress = |_: &str, _: usize, _: usize| {};
+    let fixture = crate::fixture::fixture(3);
+    let header = ParamsHeader { filtered: true, circuit_hash: Some(fixture.circuit.r1cs.hash()) };
+    let mut data = vec![];
+    write_params(&fixture.params, &header, &mut data).unwrap();
+    for threads in 1..4 {
+        let (loaded_header, loaded) = load_params_with_header(Cursor::new(&data), threads, &no_progress).unwrap();
+        assert_eq!(loaded_header.circuit_hash, header.circuit_hash);
+        let mut written = vec![];
+        write_params(&loaded, &loaded_header, &mut written).unwrap();
+        assert_eq!(written, data);
+    }
+    // legacy params without header
+    let mut legacy = vec![];
+    fixture.params.write(&mut legacy).unwrap();
+    assert!(load_params_with_header(Cursor::new(&legacy), 2, &no_progress).is_ok());
+    // a corrupted point or truncated file fails the same way as the sequential reader
+    let mut corrupted = data.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 1;
+    for data in [&corrupted[..], &data[..data.len() / 2]] {
+        let expected = crate::circom_circuit::load_params_with_header(Cursor::new(data)).err().unwrap();
+        assert_eq!(format!("{:?}", load_params_with_header(Cursor::new(data), 2, &no_progress).err().unwrap()), format!("{:?}", expected));
+    }
+
+    // more constraints than threads, so every thread gets a chunk
+    let mut r1cs = fixture.circuit.r1cs.clone();
+    r1cs.constraints = r1cs.constraints.iter().cycle().take(5).cloned().collect();
+    let recorded = crate::recorder::RecordedCircuit::<Bn256> { r1cs, witness: None, names: vec![], components: vec![] };
+    let mut data = vec![];
+    crate::r1cs_writer::write(&mut data, &recorded.r1cs_file()).unwrap();
+    let sequential = r1cs_reader::read(Cursor::new(&data)).unwrap();
+    let parallel = read_r1cs(Cursor::new(&data), 2, &no_progress).unwrap();
+    assert_eq!(parallel.constraints, sequential.constraints);
+    assert_eq!(parallel.wire_mapping, sequential.wire_mapping);
+    assert!(read_r1cs(Cursor::new(&data[..data.len() - 8]), 2, &no_progress).is_err());
+
+    let filename = std::env::temp_dir().join("zkutil_parallel_malformed.json").to_string_lossy().to_string();
+    std::fs::write(&filename, r#"{"constraints": [[{"2": "x"}, {}, {}]], "nPubInputs": 0, "nOutputs": 1, "nVars": 3}"#).unwrap();
+    assert_eq!(load_r1cs_file_with_header(&filename, 2, &no_progress).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    std::fs::remove_file(&filename).unwrap();
+}
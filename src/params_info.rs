@@ -0,0 +1,100 @@
+extern crate bellman_ce;
+
+use std::io;
+use sha2::{Digest, Sha256};
+use bellman_ce::{
+    groth16::{Parameters, VerifyingKey},
+    pairing::{CurveAffine, bn256::Bn256},
+};
+
+use crate::circom_circuit::{ParamsHeader, load_params_file_with_header};
+use crate::utils::{bytes_to_hex, p1_to_vec, p2_to_vec};
+
+/// Summary of trusted setup parameters
+#[derive(Serialize, Debug)]
+pub struct ParamsInfo {
+    pub filtered: bool,
+    /// Number of public inputs, one less than IC length
+    pub public_inputs: usize,
+    pub ic: usize,
+    pub h: usize,
+    pub l: usize,
+    pub a: usize,
+    pub b_g1: usize,
+    pub b_g2: usize,
+    pub vk_hash: String,
+    /// Hash of the circuit params were generated for, absent in older files
+    pub circuit_hash: Option<String>,
+    pub alpha_g1: Vec<String>,
+    pub beta_g1: Vec<String>,
+    pub beta_g2: Vec<Vec<String>>,
+    pub gamma_g2: Vec<Vec<String>>,
+    pub delta_g1: Vec<String>,
+    pub delta_g2: Vec<Vec<String>>,
+}
+
+impl ParamsInfo {
+    pub fn new(params: &Parameters<Bn256>, header: &ParamsHeader) -> ParamsInfo {
+        let vk = &params.vk;
+        ParamsInfo {
+            filtered: header.filtered,
+            public_inputs: vk.ic.len() - 1,
+            ic: vk.ic.len(),
+            h: params.h.len(),
+            l: params.l.len(),
+            a: params.a.len(),
+            b_g1: params.b_g1.len(),
+            b_g2: params.b_g2.len(),
+            vk_hash: vk_hash(vk),
+            circuit_hash: header.circuit_hash.map(|h| bytes_to_hex(&h)),
+            alpha_g1: p1_to_vec(&vk.alpha_g1),
+            beta_g1: p1_to_vec(&vk.beta_g1),
+            beta_g2: p2_to_vec(&vk.beta_g2),
+            gamma_g2: p2_to_vec(&vk.gamma_g2),
+            delta_g1: p1_to_vec(&vk.delta_g1),
+            delta_g2: p2_to_vec(&vk.delta_g2),
+        }
+    }
+}
+
+/// SHA-256 of the verifying key as serialized in params.bin, hex encoded.
+/// Zero IC points are left out as `filter_params` does, so filtering or normalizing params keeps it the same.
+pub fn vk_hash(vk: &VerifyingKey<Bn256>) -> String {
+    let mut vk = vk.clone();
+    vk.ic.retain(|x| !x.is_zero());
+    let mut data = vec![];
+    vk.write(&mut data).unwrap();
+    bytes_to_hex(&Sha256::digest(&data))
+}
+
+pub fn load_params_info_file(filename: &str) -> io::Result<ParamsInfo> {
+    let (header, params) = load_params_file_with_header(filename)?;
+    Ok(ParamsInfo::new(&params, &header))
+}
+
+#[test]
+fn vk_hash_ignores_filtering() {
+    use crate::circom_circuit::filter_params;
+
+    let mut fixture = crate::fixture::fixture(0);
+    let params = fixture.params.clone();
+    let mut filtered = params.clone();
+    filter_params(&mut filtered);
+    let info = ParamsInfo::new(&filtered, &ParamsHeader { filtered: true, circuit_hash: None });
+    assert!(info.filtered);
+    assert_eq!(info.vk_hash, vk_hash(&params.vk));
+    assert_eq!(info.vk_hash.len(), 64);
+    assert_eq!((info.public_inputs, info.h, info.l, info.a, info.b_g1, info.b_g2), (1, 3, 1, 3, 1, 1));
+
+    let other = crate::circom_circuit::generate_random_parameters(fixture.circuit, &mut fixture.rng).unwrap();
+    assert_ne!(vk_hash(&other.vk), info.vk_hash);
+
+    // filtering drops zero IC points, the hash doesn't see them
+    let mut with_zero = params.clone();
+    with_zero.vk.ic.push(CurveAffine::zero());
+    let mut filtered = with_zero.clone();
+    filter_params(&mut filtered);
+    assert_eq!(filtered.vk.ic.len(), params.vk.ic.len());
+    assert_eq!(vk_hash(&with_zero.vk), vk_hash(&filtered.vk));
+    assert_eq!(vk_hash(&with_zero.vk), info.vk_hash);
+}
@@ -0,0 +1,91 @@
+extern crate bellman_ce;
+extern crate rand;
+
+use std::fs::OpenOptions;
+use std::io::{self, BufReader, Read};
+use rand::Rng;
+use bellman_ce::{
+    Circuit,
+    SynthesisError,
+    groth16::{
+        Parameters,
+        Proof,
+        create_random_proof,
+    },
+    pairing::{
+        Engine,
+        bn256::Bn256,
+    },
+};
+
+use crate::circom_circuit::{ParamsHeader, R1CS, check_circuit_hash, filter_params, load_params_with_header};
+use crate::parallel_loader::{self, Progress};
+use crate::verifier::Verifier;
+
+/// Holds proving parameters that are already filtered, meant to be kept around
+/// for generating many proofs.
+pub struct Prover<E: Engine> {
+    params: Parameters<E>,
+    header: ParamsHeader,
+}
+
+impl<E: Engine> Prover<E> {
+    pub fn new(params: Parameters<E>) -> Prover<E> {
+        Self::with_header(params, ParamsHeader::default())
+    }
+
+    /// Creates a prover from params that are known to have no points at infinity
+    pub fn new_filtered(params: Parameters<E>) -> Prover<E> {
+        Self::with_header(params, ParamsHeader { filtered: true, circuit_hash: None })
+    }
+
+    /// Creates a prover from params loaded along with their header, filtering
+    /// is skipped if params are marked as already filtered
+    pub fn with_header(mut params: Parameters<E>, mut header: ParamsHeader) -> Prover<E> {
+        if !header.filtered {
+            filter_params(&mut params);
+            header.filtered = true;
+        }
+        Prover { params, header }
+    }
+
+    pub fn params(&self) -> &Parameters<E> {
+        &self.params
+    }
+
+    pub fn header(&self) -> &ParamsHeader {
+        &self.header
+    }
+
+    /// Checks that params were generated for the circuit
+    pub fn check_circuit(&self, r1cs: &R1CS<E>) -> io::Result<()> {
+        check_circuit_hash(&self.header, r1cs)
+    }
+
+    pub fn verifier(&self) -> Verifier<E> {
+        Verifier::new(&self.params.vk)
+    }
+
+    pub fn prove<C: Circuit<E>, R: Rng>(&self, circuit: C, mut rng: R) -> Result<Proof<E>, SynthesisError> {
+        create_random_proof(circuit, &self.params, &mut rng)
+    }
+}
+
+/// Loads params for a prover, filtering is skipped if the file is marked as already filtered
+pub fn load_prover_file(filename: &str) -> io::Result<Prover<Bn256>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    load_prover(BufReader::new(reader))
+}
+
+pub fn load_prover<R: Read>(reader: R) -> io::Result<Prover<Bn256>> {
+    let (header, params) = load_params_with_header(reader)?;
+    Ok(Prover::with_header(params, header))
+}
+
+/// Same as `load_prover_file`, points are decoded on `threads` threads
+pub fn load_prover_file_parallel(filename: &str, threads: usize, progress: Progress) -> io::Result<Prover<Bn256>> {
+    let (header, params) = parallel_loader::load_params_file_with_header(filename, threads, progress)?;
+    Ok(Prover::with_header(params, header))
+}
@@ -0,0 +1,187 @@
+//! Labeled public signals JSON (`public.labeled.json`). Public wires start at 1,
+//! circom puts the `n_pub_out` outputs first and then the `n_pub_in` public inputs.
+extern crate bellman_ce;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::collections::HashMap;
+use serde_json::Value;
+use bellman_ce::pairing::{
+    Engine,
+    ff::PrimeField,
+};
+
+use crate::solver::inputs_from_json;
+use crate::utils::repr_to_big;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LabeledSignal {
+    pub wire: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LabeledPublic {
+    pub outputs: Vec<LabeledSignal>,
+    pub inputs: Vec<LabeledSignal>,
+}
+
+fn invalid(e: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Labels public signals with sym names, all signals are listed as outputs if `n_pub_out` is unknown
+pub fn label_public<E: Engine>(public: &[E::Fr], n_pub_out: Option<usize>, names: &[Option<String>]) -> LabeledPublic {
+    let n_pub_out = n_pub_out.unwrap_or(public.len()).min(public.len());
+    let signal = |(i, value): (usize, &E::Fr)| LabeledSignal {
+        wire: i + 1,
+        name: names.get(i + 1).cloned().flatten(),
+        value: repr_to_big(value.into_repr()),
+    };
+    LabeledPublic {
+        outputs: public[..n_pub_out].iter().enumerate().map(signal).collect(),
+        inputs: public[n_pub_out..].iter().enumerate().map(|(i, v)| signal((i + n_pub_out, v))).collect(),
+    }
+}
+
+pub fn labeled_public_json<E: Engine>(public: &[E::Fr], n_pub_out: Option<usize>, names: &[Option<String>]) -> String {
+    serde_json::to_string_pretty(&label_public::<E>(public, n_pub_out, names)).unwrap()
+}
+
+impl LabeledPublic {
+    /// Public inputs in verifier order, signals must cover consecutive wires starting at 1
+    pub fn values<E: Engine>(&self) -> io::Result<Vec<E::Fr>> {
+        self.outputs.iter().chain(self.inputs.iter()).enumerate()
+            .map(|(i, signal)| {
+                if signal.wire != i + 1 {
+                    return Err(invalid(format!("Public signal {} has wire {}, expected {}", i, signal.wire, i + 1)));
+                }
+                E::Fr::from_str(&signal.value)
+                    .ok_or_else(|| invalid(format!("Invalid field element {} for wire {}", signal.value, signal.wire)))
+            })
+            .collect()
+    }
+
+    /// Checks signal names and output/input split against the circuit, names missing on either side are skipped
+    pub fn check_names(&self, n_pub_out: Option<usize>, names: &[Option<String>]) -> io::Result<()> {
+        if let Some(n_pub_out) = n_pub_out {
+            if n_pub_out != self.outputs.len() {
+                return Err(invalid(format!("Circuit has {} public outputs, found {}", n_pub_out, self.outputs.len())));
+            }
+        }
+        for signal in self.outputs.iter().chain(self.inputs.iter()) {
+            if let (Some(name), Some(Some(expected))) = (&signal.name, names.get(signal.wire)) {
+                if name != expected {
+                    return Err(invalid(format!("Public wire {} is {} in the circuit, found {}", signal.wire, expected, name)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads either a flat public.json array or the labeled form
+pub fn load_public_json<E: Engine>(data: &str) -> io::Result<(Vec<E::Fr>, Option<LabeledPublic>)> {
+    if data.trim_start().starts_with('{') {
+        let labeled: LabeledPublic = serde_json::from_str(data)?;
+        return Ok((labeled.values::<E>()?, Some(labeled)));
+    }
+    let inputs: Vec<String> = serde_json::from_str(data)?;
+    let inputs = inputs.iter()
+        .map(|x| E::Fr::from_str(x).ok_or_else(|| invalid(format!("Invalid field element {}", x))))
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok((inputs, None))
+}
+
+pub fn load_public_json_file<E: Engine>(filename: &str) -> io::Result<(Vec<E::Fr>, Option<LabeledPublic>)> {
+    load_public_json::<E>(&fs::read_to_string(filename)?)
+}
+
+/// Public signal that differs from the expected value
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicMismatch {
+    pub wire: usize,
+    pub name: Option<String>,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for PublicMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} (wire {}): expected {}, got {}", name, self.wire, self.expected, self.actual),
+            None => write!(f, "wire {}: expected {}, got {}", self.wire, self.expected, self.actual),
+        }
+    }
+}
+
+/// Expected values by wire. Accepts a full public.json array, its labeled form, or
+/// a partial object keyed by wire index or signal name like solver inputs.
+pub fn expected_public_from_json<E: Engine>(expected: &Value, names: &[Option<String>]) -> io::Result<HashMap<usize, E::Fr>> {
+    let values = match expected {
+        Value::Array(_) => load_public_json::<E>(&expected.to_string())?.0,
+        Value::Object(object) if object.contains_key("outputs") && object.contains_key("inputs") => {
+            serde_json::from_value::<LabeledPublic>(expected.clone())?.values::<E>()?
+        }
+        _ => return inputs_from_json::<E>(expected, names),
+    };
+    Ok(values.into_iter().enumerate().map(|(i, value)| (i + 1, value)).collect())
+}
+
+/// Compares public signals with expected values, returns mismatches ordered by wire
+pub fn check_expected_public<E: Engine>(public: &[E::Fr], expected: &HashMap<usize, E::Fr>, names: &[Option<String>]) -> io::Result<Vec<PublicMismatch>> {
+    let mut wires = expected.keys().cloned().collect::<Vec<_>>();
+    wires.sort_unstable();
+    if let Some(wire) = wires.iter().find(|wire| **wire == 0 || **wire > public.len()) {
+        return Err(invalid(format!("Wire {} is not a public signal", wire)));
+    }
+    Ok(wires.into_iter()
+        .filter(|wire| public[wire - 1] != expected[wire])
+        .map(|wire| PublicMismatch {
+            wire,
+            name: names.get(wire).cloned().flatten(),
+            expected: repr_to_big(expected[&wire].into_repr()),
+            actual: repr_to_big(public[wire - 1].into_repr()),
+        })
+        .collect())
+}
+
+#[test]
+fn labeled_roundtrip() {
+    use bellman_ce::pairing::bn256::{Bn256, Fr};
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+    let public = vec![fr("9"), fr("3"), fr("4")];
+    let names = vec![None, Some("main.out".to_string()), Some("main.a".to_string())];
+
+    let json = labeled_public_json::<Bn256>(&public, Some(1), &names);
+    let (values, labeled) = load_public_json::<Bn256>(&json).unwrap();
+    assert_eq!(values, public);
+    let labeled = labeled.unwrap();
+    assert_eq!(labeled.outputs, vec![LabeledSignal { wire: 1, name: Some("main.out".to_string()), value: "9".to_string() }]);
+    assert_eq!(labeled.inputs[1].name, None);
+    assert!(labeled.check_names(Some(1), &names).is_ok());
+    assert!(labeled.check_names(Some(2), &names).is_err());
+    assert!(labeled.check_names(None, &[None, Some("main.x".to_string())]).is_err());
+
+    let (values, labeled) = load_public_json::<Bn256>(r#"["9", "3", "4"]"#).unwrap();
+    assert_eq!((values, labeled), (public, None));
+}
+
+#[test]
+fn expected_public() {
+    use bellman_ce::pairing::bn256::{Bn256, Fr};
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+    let public = vec![fr("9"), fr("3")];
+    let names = vec![None, Some("main.out".to_string()), Some("main.a".to_string())];
+
+    let expected = expected_public_from_json::<Bn256>(&serde_json::json!({ "out": "9" }), &names).unwrap();
+    assert_eq!(check_expected_public::<Bn256>(&public, &expected, &names).unwrap(), vec![]);
+    let expected = expected_public_from_json::<Bn256>(&serde_json::json!(["9", "4"]), &names).unwrap();
+    let mismatches = check_expected_public::<Bn256>(&public, &expected, &names).unwrap();
+    assert_eq!(mismatches.iter().map(|m| m.to_string()).collect::<Vec<_>>(), vec!["main.a (wire 2): expected 4, got 3"]);
+    let expected = expected_public_from_json::<Bn256>(&serde_json::json!({ "3": "1" }), &names).unwrap();
+    assert!(check_expected_public::<Bn256>(&public, &expected, &names).is_err());
+}
@@ -0,0 +1,293 @@
+//! Python bindings, enabled with the `python` feature.
+//!
+//! ```python
+//! import zkutil
+//! params = zkutil.load_params("params.bin")
+//! circuit = zkutil.R1CS.from_file("circuit.r1cs")
+//! proof, public = zkutil.prove(params, circuit, "witness.wtns")
+//! assert zkutil.verify(params.vk, proof, public)
+//! ```
+extern crate bellman_ce;
+
+use std::io;
+use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyFileNotFoundError, PyIOError, PyValueError};
+use pyo3::types::PyString;
+use bellman_ce::{
+    SynthesisError,
+    groth16::Proof,
+    pairing::{
+        ff::PrimeField,
+        bn256::{Bn256, Fr},
+    },
+};
+
+use crate::circom_circuit::{
+    CircomCircuit,
+    CircomCircuitRef,
+    ParamsHeader,
+    R1CS,
+    create_rng,
+    create_verifier_sol,
+    generate_random_parameters,
+    parse_proof_json,
+    proof_to_json,
+    proving_key_json,
+    r1cs_from_bin_file,
+    try_r1cs_from_json_file,
+    try_witness_from_json_file,
+    verification_key_json,
+    witness_from_bin_file,
+    write_params_file,
+};
+use crate::prover::{Prover, load_prover_file};
+use crate::utils::{proof_to_hex, repr_to_big, synthesis_error_message};
+use crate::verifier::{Verifier, load_verifier_file};
+
+create_exception!(zkutil, ZkutilError, PyException);
+
+fn io_error(e: io::Error) -> PyErr {
+    match e.kind() {
+        io::ErrorKind::NotFound => PyFileNotFoundError::new_err(e.to_string()),
+        io::ErrorKind::InvalidData => PyValueError::new_err(e.to_string()),
+        _ => PyIOError::new_err(e.to_string()),
+    }
+}
+
+fn synthesis_error(e: SynthesisError) -> PyErr {
+    ZkutilError::new_err(synthesis_error_message(&e))
+}
+
+fn json_error(e: serde_json::Error) -> PyErr {
+    ZkutilError::new_err(e.to_string())
+}
+
+/// Converts a list of ints or decimal strings to field elements
+fn to_fields(values: &Bound<'_, PyAny>) -> PyResult<Vec<Fr>> {
+    values.try_iter()?
+        .map(|x| {
+            let x = x?.str()?;
+            let s = x.to_cow()?;
+            Fr::from_str(&s).ok_or_else(|| PyValueError::new_err(format!("Invalid field element {}", s)))
+        })
+        .collect()
+}
+
+fn from_fields(values: &[Fr]) -> Vec<String> {
+    values.iter().map(|x| repr_to_big(x.into_repr())).collect()
+}
+
+/// Trusted setup parameters
+#[pyclass(name = "Params", module = "zkutil")]
+struct PyParams {
+    prover: Prover<Bn256>,
+}
+
+#[pymethods]
+impl PyParams {
+    #[getter]
+    fn vk(&self) -> PyVerifyingKey {
+        PyVerifyingKey { verifier: self.prover.verifier() }
+    }
+
+    #[getter]
+    fn num_public_inputs(&self) -> usize {
+        self.prover.params().vk.ic.len() - 1
+    }
+
+    /// Saves params in the same format as `zkutil setup`
+    fn save(&self, path: &str) -> PyResult<()> {
+        write_params_file(self.prover.params(), self.prover.header(), path).map_err(io_error)
+    }
+
+    /// Generates a proof, `witness` is either a file name or a list of values.
+    /// Returns a tuple of proof and public inputs.
+    fn prove(&self, py: Python<'_>, circuit: &PyR1CS, witness: &Bound<'_, PyAny>) -> PyResult<(PyProof, Vec<String>)> {
+        self.prover.check_circuit(&circuit.r1cs).map_err(io_error)?;
+        let witness = match witness.downcast::<PyString>() {
+            Ok(path) => read_witness(&path.to_cow()?)?,
+            Err(_) => to_fields(witness)?,
+        };
+        if witness.len() != circuit.r1cs.num_variables {
+            return Err(PyValueError::new_err(format!("Witness has {} values, circuit has {} wires", witness.len(), circuit.r1cs.num_variables)));
+        }
+        let circuit = CircomCircuitRef {
+            r1cs: &circuit.r1cs,
+            witness: Some(&witness),
+            wire_mapping: None,
+        };
+        // proving takes a while, let other python threads run meanwhile
+        let proof = py.allow_threads(|| self.prover.prove(circuit, create_rng()))
+            .map_err(synthesis_error)?;
+        let public = circuit.get_public_inputs().unwrap();
+        Ok((PyProof { proof }, from_fields(&public)))
+    }
+}
+
+/// Verifying key with precomputed pairing
+#[pyclass(name = "VerifyingKey", module = "zkutil")]
+struct PyVerifyingKey {
+    verifier: Verifier<Bn256>,
+}
+
+#[pymethods]
+impl PyVerifyingKey {
+    /// Loads a prepared verifying key created by `zkutil prepare-vk`
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<Self> {
+        Ok(PyVerifyingKey { verifier: load_verifier_file(path).map_err(io_error)? })
+    }
+}
+
+/// Circuit constraints loaded from circom .r1cs or .json file
+#[pyclass(name = "R1CS", module = "zkutil")]
+struct PyR1CS {
+    r1cs: R1CS<Bn256>,
+}
+
+#[pymethods]
+impl PyR1CS {
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<Self> {
+        let r1cs = if path.ends_with("json") {
+            try_r1cs_from_json_file(path).map_err(io_error)?
+        } else {
+            r1cs_from_bin_file(path).map_err(io_error)?.0
+        };
+        Ok(PyR1CS { r1cs })
+    }
+
+    #[getter]
+    fn num_inputs(&self) -> usize {
+        self.r1cs.num_inputs
+    }
+
+    #[getter]
+    fn num_aux(&self) -> usize {
+        self.r1cs.num_aux
+    }
+
+    #[getter]
+    fn num_variables(&self) -> usize {
+        self.r1cs.num_variables
+    }
+
+    #[getter]
+    fn num_constraints(&self) -> usize {
+        self.r1cs.constraints.len()
+    }
+}
+
+/// Groth16 proof
+#[pyclass(name = "Proof", module = "zkutil")]
+struct PyProof {
+    proof: Proof<Bn256>,
+}
+
+#[pymethods]
+impl PyProof {
+    #[staticmethod]
+    fn from_json(data: &str) -> PyResult<Self> {
+        Ok(PyProof { proof: parse_proof_json(data).map_err(io_error)? })
+    }
+
+    /// Proof in snarkjs compatible JSON format
+    fn to_json(&self) -> PyResult<String> {
+        proof_to_json(&self.proof).map_err(json_error)
+    }
+
+    /// Proof as a hex string suitable for verifier contract calldata
+    fn to_calldata(&self) -> String {
+        proof_to_hex(&self.proof)
+    }
+}
+
+/// Generates random parameters for the circuit, not suitable for production use
+#[pyfunction]
+fn setup(circuit: &PyR1CS) -> PyResult<PyParams> {
+    let header = ParamsHeader { filtered: false, circuit_hash: Some(circuit.r1cs.hash()) };
+    let circuit = CircomCircuit {
+        r1cs: circuit.r1cs.clone(),
+        witness: None,
+        wire_mapping: None,
+    };
+    let params = generate_random_parameters(circuit, create_rng()).map_err(synthesis_error)?;
+    Ok(PyParams { prover: Prover::with_header(params, header) })
+}
+
+#[pyfunction]
+fn load_params(path: &str) -> PyResult<PyParams> {
+    Ok(PyParams { prover: load_prover_file(path).map_err(io_error)? })
+}
+
+fn read_witness(path: &str) -> PyResult<Vec<Fr>> {
+    if path.ends_with("json") {
+        try_witness_from_json_file::<Bn256>(path).map_err(io_error)
+    } else {
+        witness_from_bin_file::<Bn256>(path).map_err(io_error)
+    }
+}
+
+/// Generates a proof, same as `Params.prove`
+#[pyfunction]
+fn prove(py: Python<'_>, params: &PyParams, circuit: &PyR1CS, witness: &Bound<'_, PyAny>) -> PyResult<(PyProof, Vec<String>)> {
+    params.prove(py, circuit, witness)
+}
+
+/// Loads witness from .wtns or .json file as a list of decimal strings
+#[pyfunction]
+fn load_witness(path: &str) -> PyResult<Vec<String>> {
+    Ok(from_fields(&read_witness(path)?))
+}
+
+/// Verifies a proof, `proof` is either a `Proof` or its JSON
+#[pyfunction]
+fn verify(vk: &PyVerifyingKey, proof: &Bound<'_, PyAny>, inputs: &Bound<'_, PyAny>) -> PyResult<bool> {
+    let proof = match proof.downcast::<PyProof>() {
+        Ok(proof) => proof.borrow().proof.clone(),
+        Err(_) => parse_proof_json(&proof.str()?.to_cow()?).map_err(io_error)?,
+    };
+    vk.verifier.verify(&proof, &to_fields(inputs)?).map_err(synthesis_error)
+}
+
+/// Verification key in snarkjs compatible JSON format
+#[pyfunction]
+fn verification_key_to_json(params: &PyParams) -> PyResult<String> {
+    verification_key_json(params.prover.params()).map_err(json_error)
+}
+
+/// Proving key in snarkjs/websnark compatible JSON format
+#[pyfunction]
+fn proving_key_to_json(params: &PyParams, circuit: &PyR1CS) -> PyResult<String> {
+    let circuit = CircomCircuit {
+        r1cs: circuit.r1cs.clone(),
+        witness: None,
+        wire_mapping: None,
+    };
+    proving_key_json(params.prover.params(), circuit).map_err(json_error)
+}
+
+/// Solidity verifier contract source
+#[pyfunction]
+fn verifier_contract(params: &PyParams) -> String {
+    create_verifier_sol(params.prover.params())
+}
+
+#[pymodule]
+fn zkutil(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("ZkutilError", m.py().get_type::<ZkutilError>())?;
+    m.add_class::<PyParams>()?;
+    m.add_class::<PyVerifyingKey>()?;
+    m.add_class::<PyR1CS>()?;
+    m.add_class::<PyProof>()?;
+    m.add_function(wrap_pyfunction!(load_params, m)?)?;
+    m.add_function(wrap_pyfunction!(load_witness, m)?)?;
+    m.add_function(wrap_pyfunction!(setup, m)?)?;
+    m.add_function(wrap_pyfunction!(prove, m)?)?;
+    m.add_function(wrap_pyfunction!(verify, m)?)?;
+    m.add_function(wrap_pyfunction!(verification_key_to_json, m)?)?;
+    m.add_function(wrap_pyfunction!(proving_key_to_json, m)?)?;
+    m.add_function(wrap_pyfunction!(verifier_contract, m)?)?;
+    Ok(())
+}
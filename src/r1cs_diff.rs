@@ -0,0 +1,159 @@
+extern crate bellman_ce;
+
+use std::io;
+use std::collections::HashMap;
+use bellman_ce::pairing::{
+    Engine,
+    ff::{Field, PrimeField},
+    bn256::Bn256,
+};
+
+use crate::circom_circuit::R1CS;
+use crate::circuit_info::load_r1cs_file_with_header;
+use crate::optimizer::constraint_key;
+use crate::r1cs_reader::Header;
+use crate::utils::repr_to_big;
+
+/// Circuit sizes compared by diff, public outputs and inputs are only known for .r1cs files
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CircuitShape {
+    pub wires: usize,
+    pub public: usize,
+    pub public_outputs: Option<u32>,
+    pub public_inputs: Option<u32>,
+    pub private_inputs: Option<u32>,
+    pub constraints: usize,
+}
+
+impl CircuitShape {
+    pub fn new<E: Engine>(r1cs: &R1CS<E>, header: Option<&Header>) -> CircuitShape {
+        CircuitShape {
+            wires: r1cs.num_variables,
+            public: r1cs.num_inputs - 1,
+            public_outputs: header.map(|h| h.n_pub_out),
+            public_inputs: header.map(|h| h.n_pub_in),
+            private_inputs: header.map(|h| h.n_prv_in),
+            constraints: r1cs.constraints.len(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct R1CSDiff {
+    pub old: CircuitShape,
+    pub new: CircuitShape,
+    /// Indices of old constraints missing in the new circuit
+    pub removed: Vec<usize>,
+    /// Indices of new constraints missing in the old circuit
+    pub added: Vec<usize>,
+    /// Number and layout of public inputs didn't change
+    pub public_interface_unchanged: bool,
+    /// Circuit hashes match, so params generated for the old circuit are valid for the new one
+    pub structurally_identical: bool,
+}
+
+impl R1CSDiff {
+    pub fn new<E: Engine>(old: &R1CS<E>, old_header: Option<&Header>, new: &R1CS<E>, new_header: Option<&Header>) -> R1CSDiff {
+        let old_shape = CircuitShape::new(old, old_header);
+        let new_shape = CircuitShape::new(new, new_header);
+
+        // match constraints as multisets, so that reordering alone isn't reported
+        let mut unmatched: HashMap<_, Vec<usize>> = HashMap::new();
+        for (i, constraint) in old.constraints.iter().enumerate().rev() {
+            unmatched.entry(constraint_key::<E>(constraint)).or_default().push(i);
+        }
+        let mut added = vec![];
+        for (i, constraint) in new.constraints.iter().enumerate() {
+            let matched = unmatched.get_mut(&constraint_key::<E>(constraint)).and_then(|indices| indices.pop());
+            if matched.is_none() {
+                added.push(i);
+            }
+        }
+        let mut removed = unmatched.into_values().flatten().collect::<Vec<_>>();
+        removed.sort_unstable();
+
+        // outputs and inputs split is only compared if both headers are known
+        let same = |a: Option<u32>, b: Option<u32>| a.is_none() || b.is_none() || a == b;
+        let public_interface_unchanged = old_shape.public == new_shape.public
+            && same(old_shape.public_outputs, new_shape.public_outputs)
+            && same(old_shape.public_inputs, new_shape.public_inputs);
+        R1CSDiff {
+            old: old_shape,
+            new: new_shape,
+            removed,
+            added,
+            public_interface_unchanged,
+            structurally_identical: old.hash() == new.hash(),
+        }
+    }
+}
+
+/// Linear combination as `c1*w1 + c2*w2`, coefficients equal to 1 are omitted
+pub fn format_lc<E: Engine>(lc: &[(usize, E::Fr)]) -> String {
+    if lc.is_empty() {
+        return "0".to_string();
+    }
+    lc.iter()
+        .map(|(index, coeff)| if *coeff == E::Fr::one() {
+            format!("w{}", index)
+        } else {
+            format!("{}*w{}", repr_to_big(coeff.into_repr()), index)
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+pub fn diff_r1cs_files(old: &str, new: &str) -> io::Result<(R1CSDiff, R1CS<Bn256>, R1CS<Bn256>)> {
+    let (old_r1cs, old_header) = load_r1cs_file_with_header(old)?;
+    let (new_r1cs, new_header) = load_r1cs_file_with_header(new)?;
+    let diff = R1CSDiff::new(&old_r1cs, old_header.as_ref(), &new_r1cs, new_header.as_ref());
+    Ok((diff, old_r1cs, new_r1cs))
+}
+
+#[test]
+fn diff_reordered_and_changed() {
+    use bellman_ce::pairing::bn256::Fr;
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+
+    let old = R1CS::<Bn256> {
+        num_inputs: 2,
+        num_aux: 2,
+        num_variables: 4,
+        constraints: vec![
+            (vec![(2, fr("1"))], vec![(2, fr("1"))], vec![(3, fr("1"))]),
+            (vec![(3, fr("1"))], vec![(2, fr("1"))], vec![(1, fr("1"))]),
+        ],
+    };
+    let mut reordered = old.clone();
+    reordered.constraints.reverse();
+    reordered.constraints[0].0.push((3, fr("0")));
+    reordered.constraints[0] = (reordered.constraints[0].1.clone(), reordered.constraints[0].0.clone(), reordered.constraints[0].2.clone());
+    let diff = R1CSDiff::new(&old, None, &reordered, None);
+    assert!(diff.added.is_empty() && diff.removed.is_empty());
+    assert!(diff.public_interface_unchanged);
+    assert!(!diff.structurally_identical);
+    assert!(R1CSDiff::new(&old, None, &old, None).structurally_identical);
+
+    let mut changed = old.clone();
+    changed.constraints[1].2 = vec![(1, fr("2"))];
+    changed.constraints.push(changed.constraints[0].clone());
+    let diff = R1CSDiff::new(&old, None, &changed, None);
+    assert_eq!((diff.removed, diff.added), (vec![1], vec![1, 2]));
+    assert_eq!(format_lc::<Bn256>(&changed.constraints[1].2), "2*w1");
+}
+
+#[test]
+fn diff_missing_and_malformed_files() {
+    let dir = std::env::temp_dir();
+    let filename = |name: &str| dir.join(name).to_string_lossy().to_string();
+    let (valid, malformed) = (filename("zkutil_diff_valid.json"), filename("zkutil_diff_malformed.json"));
+    std::fs::write(&valid, r#"{"constraints": [[{"2": "1"}, {"2": "1"}, {"1": "1"}]], "nPubInputs": 0, "nOutputs": 1, "nVars": 3}"#).unwrap();
+    std::fs::write(&malformed, r#"{"constraints": [[{"2": "x"}, {}, {}]], "nPubInputs": 0, "nOutputs": 1, "nVars": 3}"#).unwrap();
+
+    let (diff, _, _) = diff_r1cs_files(&valid, &valid).unwrap();
+    assert!(diff.structurally_identical);
+    assert_eq!(diff_r1cs_files(&valid, &malformed).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(diff_r1cs_files(&filename("zkutil_diff_missing.r1cs"), &valid).err().unwrap().kind(), io::ErrorKind::NotFound);
+    std::fs::remove_file(&valid).unwrap();
+    std::fs::remove_file(&malformed).unwrap();
+}
@@ -70,15 +70,20 @@ fn read_constraint_vec<R: Read, E:Engine>(mut reader: R, header: &Header) -> Res
     Ok(vec)
 }
 
+/// Reads the next constraint of the constraints section
+pub fn read_constraint<R: Read, E: Engine>(mut reader: R, header: &Header) -> Result<Constraint<E>> {
+    Ok((
+        read_constraint_vec::<&mut R, E>(&mut reader, header)?,
+        read_constraint_vec::<&mut R, E>(&mut reader, header)?,
+        read_constraint_vec::<&mut R, E>(&mut reader, header)?,
+    ))
+}
+
 fn read_constraints<R: Read, E: Engine>(mut reader: R, size: u64, header: &Header) -> Result<Vec<Constraint<E>>> {
     // todo check section size
     let mut vec = Vec::with_capacity(header.n_constraints as usize);
     for _ in 0..header.n_constraints {
-        vec.push((
-             read_constraint_vec::<&mut R, E>(&mut reader, &header)?,
-             read_constraint_vec::<&mut R, E>(&mut reader, &header)?,
-             read_constraint_vec::<&mut R, E>(&mut reader, &header)?,
-        ));
+        vec.push(read_constraint::<&mut R, E>(&mut reader, header)?);
     }
     Ok(vec)
 }
@@ -97,7 +102,22 @@ fn read_map<R: Read>(mut reader: R, size: u64, header: &Header) -> Result<Vec<u6
     Ok(vec)
 }
 
-pub fn read<R: Read + Seek>(mut reader: R) -> Result<R1CSFile<Bn256>> {
+/// File offset and size of a section
+#[derive(Clone, Copy)]
+pub struct Section {
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Header and section locations, lets constraints be read lazily
+pub struct Layout {
+    pub version: u32,
+    pub header: Header,
+    pub constraints: Section,
+    pub wire2label: Section,
+}
+
+pub fn read_layout<R: Read + Seek>(mut reader: R) -> Result<Layout> {
     let mut magic = [0u8; 4];
     reader.read_exact(&mut magic)?;
     if magic != [0x72, 0x31, 0x63, 0x73] { // magic = "r1cs"
@@ -111,26 +131,27 @@ pub fn read<R: Read + Seek>(mut reader: R) -> Result<R1CSFile<Bn256>> {
 
     let num_sections = reader.read_u32::<LittleEndian>()?;
 
-    // section type -> file offset
-    let mut sec_offsets = HashMap::<u32, u64>::new();
-    let mut sec_sizes = HashMap::<u32, u64>::new();
+    // section type -> file offset and size
+    let mut sections = HashMap::<u32, Section>::new();
 
     // get file offset of each section
     for _ in 0..num_sections {
         let sec_type = reader.read_u32::<LittleEndian>()?;
-        let sec_size = reader.read_u64::<LittleEndian>()?;
+        let size = reader.read_u64::<LittleEndian>()?;
         let offset = reader.seek(SeekFrom::Current(0))?;
-        sec_offsets.insert(sec_type, offset);
-        sec_sizes.insert(sec_type, sec_size);
-        reader.seek(SeekFrom::Current(sec_size as i64))?;
+        sections.insert(sec_type, Section { offset, size });
+        reader.seek(SeekFrom::Current(size as i64))?;
     }
 
     let header_type = 1;
     let constraint_type = 2;
     let wire2label_type = 3;
+    let section = |sec_type: u32| sections.get(&sec_type).cloned()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Missing section {}", sec_type)));
 
-    reader.seek(SeekFrom::Start(*sec_offsets.get(&header_type).unwrap()))?;
-    let header = read_header(&mut reader, *sec_sizes.get(&header_type).unwrap())?;
+    let header_section = section(header_type)?;
+    reader.seek(SeekFrom::Start(header_section.offset))?;
+    let header = read_header(&mut reader, header_section.size)?;
     if header.field_size != 32 {
         return Err(Error::new(ErrorKind::InvalidData, "This parser only supports 32-byte fields"))
     }
@@ -138,12 +159,17 @@ pub fn read<R: Read + Seek>(mut reader: R) -> Result<R1CSFile<Bn256>> {
         return Err(Error::new(ErrorKind::InvalidData, "This parser only supports bn256"))
     }
 
-    reader.seek(SeekFrom::Start(*sec_offsets.get(&constraint_type).unwrap()))?;
-    let constraints = read_constraints::<&mut R, Bn256>(&mut reader, *sec_sizes.get(&constraint_type).unwrap(), &header)?;
+    Ok(Layout { version, header, constraints: section(constraint_type)?, wire2label: section(wire2label_type)? })
+}
+
+pub fn read<R: Read + Seek>(mut reader: R) -> Result<R1CSFile<Bn256>> {
+    let Layout { version, header, constraints, wire2label } = read_layout(&mut reader)?;
 
-    reader.seek(SeekFrom::Start(*sec_offsets.get(&wire2label_type).unwrap()))?;
-    let wire_mapping = read_map(&mut reader, *sec_sizes.get(&wire2label_type).unwrap(), &header)?;
+    reader.seek(SeekFrom::Start(constraints.offset))?;
+    let constraints = read_constraints::<&mut R, Bn256>(&mut reader, constraints.size, &header)?;
 
+    reader.seek(SeekFrom::Start(wire2label.offset))?;
+    let wire_mapping = read_map(&mut reader, wire2label.size, &header)?;
 
     Ok(R1CSFile { version, header, constraints, wire_mapping })
 }
@@ -0,0 +1,186 @@
+//! Streaming access to .r1cs files for circuits that don't fit in memory as
+//! `R1CS`: only the header and section offsets are kept, constraints are read
+//! from the file one at a time whenever they are needed.
+extern crate bellman_ce;
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Seek, SeekFrom};
+use sha2::Digest;
+use bellman_ce::{
+    Circuit,
+    ConstraintSystem,
+    SynthesisError,
+    pairing::Engine,
+};
+
+use crate::circom_circuit::{
+    Constraint,
+    ParamsHeader,
+    alloc_wires,
+    check_hash,
+    circuit_hasher,
+    enforce_constraint,
+    hash_constraint,
+    is_satisfied,
+};
+use crate::r1cs_reader::{self, Header, Layout, read_constraint};
+
+pub struct R1CSStream {
+    filename: String,
+    layout: Layout,
+}
+
+/// Iterator over constraints read from the file
+pub struct Constraints<'a, E: Engine> {
+    reader: BufReader<File>,
+    header: &'a Header,
+    remaining: usize,
+    _engine: std::marker::PhantomData<E>,
+}
+
+impl<'a, E: Engine> Iterator for Constraints<'a, E> {
+    type Item = io::Result<Constraint<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(read_constraint::<_, E>(&mut self.reader, self.header))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl R1CSStream {
+    /// Reads header and section offsets, constraints are left in the file
+    pub fn open(filename: &str) -> io::Result<R1CSStream> {
+        let reader = OpenOptions::new()
+            .read(true)
+            .open(filename)?;
+        let layout = r1cs_reader::read_layout(BufReader::new(reader))?;
+        Ok(R1CSStream { filename: filename.to_string(), layout })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.layout.header
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        (1 + self.layout.header.n_pub_out + self.layout.header.n_pub_in) as usize
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.layout.header.n_wires as usize
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.num_variables() - self.num_inputs()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.layout.header.n_constraints as usize
+    }
+
+    /// Opens the file again for every pass, so several passes can run at once
+    pub fn constraints<E: Engine>(&self) -> io::Result<Constraints<'_, E>> {
+        let mut reader = BufReader::new(File::open(&self.filename)?);
+        reader.seek(SeekFrom::Start(self.layout.constraints.offset))?;
+        Ok(Constraints {
+            reader,
+            header: &self.layout.header,
+            remaining: self.num_constraints(),
+            _engine: std::marker::PhantomData,
+        })
+    }
+
+    /// Same as `R1CS::hash` of the loaded circuit
+    pub fn hash<E: Engine>(&self) -> io::Result<[u8; 32]> {
+        let mut hasher = circuit_hasher(self.num_inputs(), self.num_aux(), self.num_constraints());
+        for constraint in self.constraints::<E>()? {
+            hash_constraint::<E>(&mut hasher, &constraint?);
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// Checks that params were generated for the circuit, see `check_circuit_hash`
+    pub fn check_circuit_hash<E: Engine>(&self, header: &ParamsHeader) -> io::Result<()> {
+        match header.circuit_hash {
+            Some(_) => check_hash(header, self.hash::<E>()?),
+            None => Ok(()),
+        }
+    }
+
+    /// Index of the first constraint that the witness doesn't satisfy
+    pub fn first_unsatisfied<E: Engine>(&self, witness: &[E::Fr]) -> io::Result<Option<usize>> {
+        for (i, constraint) in self.constraints::<E>()?.enumerate() {
+            if !is_satisfied::<E>(&constraint?, witness) {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Circuit that reads constraints from the file during synthesis instead of keeping them in memory
+pub struct StreamingCircuit<'a, E: Engine> {
+    pub r1cs: &'a R1CSStream,
+    pub witness: Option<&'a [E::Fr]>,
+}
+
+impl<'a, E: Engine> StreamingCircuit<'a, E> {
+    /// None without witness or if the witness doesn't have a value for every wire
+    pub fn get_public_inputs(&self) -> Option<Vec<E::Fr>> {
+        self.witness
+            .filter(|w| w.len() == self.r1cs.num_variables())
+            .map(|w| w[1..self.r1cs.num_inputs()].to_vec())
+    }
+}
+
+impl<'a, E: Engine> Circuit<E> for StreamingCircuit<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let num_inputs = self.r1cs.num_inputs();
+        alloc_wires(cs, num_inputs, self.r1cs.num_aux(), self.witness, None)?;
+        for (i, constraint) in self.r1cs.constraints::<E>()?.enumerate() {
+            enforce_constraint(cs, num_inputs, i, &constraint?);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn stream_matches_loaded() {
+    use bellman_ce::pairing::bn256::Bn256;
+    use crate::circom_circuit::r1cs_from_bin_file;
+    use crate::recorder::RecordedCircuit;
+
+    let mut fixture = crate::fixture::fixture(3);
+    let recorded = RecordedCircuit::<Bn256> {
+        r1cs: fixture.circuit.r1cs.clone(),
+        witness: None,
+        names: vec![],
+        components: vec![],
+    };
+    let filename = std::env::temp_dir().join("zkutil_stream_matches_loaded.r1cs").to_string_lossy().to_string();
+    recorded.write_files(&filename, None, None).unwrap();
+    let loaded = r1cs_from_bin_file(&filename).unwrap().0;
+    let stream = R1CSStream::open(&filename).unwrap();
+    assert_eq!(stream.hash::<Bn256>().unwrap(), loaded.hash());
+    assert_eq!(stream.constraints::<Bn256>().unwrap().map(|c| c.unwrap()).collect::<Vec<_>>(), loaded.constraints);
+
+    let witness = fixture.circuit.witness.clone().unwrap();
+    assert_eq!(stream.first_unsatisfied::<Bn256>(&witness).unwrap(), None);
+    assert_eq!(stream.first_unsatisfied::<Bn256>(&[witness[0], witness[2], witness[1]]).unwrap(), Some(0));
+    assert_eq!(StreamingCircuit::<Bn256> { r1cs: &stream, witness: Some(&witness[..2]) }.get_public_inputs(), None);
+
+    let streaming = StreamingCircuit { r1cs: &stream, witness: Some(&witness) };
+    let inputs = streaming.get_public_inputs().unwrap();
+    fixture.prove_and_verify(streaming, &inputs);
+
+    // only the header is read on open, a truncated or non-r1cs file fails there
+    std::fs::write(&filename, b"r1cs").unwrap();
+    assert!(R1CSStream::open(&filename).is_err());
+    std::fs::remove_file(&filename).unwrap();
+}
@@ -0,0 +1,98 @@
+use byteorder::{WriteBytesExt, LittleEndian};
+use std::io::{Result, Write};
+use bellman_ce::pairing::{
+    Engine,
+    bn256::Bn256,
+    ff::{
+        PrimeField, PrimeFieldRepr,
+    }
+};
+use crate::r1cs_reader::{Header, R1CSFile};
+
+fn write_constraint_vec<W: Write, E: Engine>(mut writer: W, vec: &[(usize, E::Fr)]) -> Result<()> {
+    writer.write_u32::<LittleEndian>(vec.len() as u32)?;
+    for (index, coeff) in vec {
+        writer.write_u32::<LittleEndian>(*index as u32)?;
+        coeff.into_repr().write_le(&mut writer)?;
+    }
+    Ok(())
+}
+
+fn write_section<W: Write>(mut writer: W, sec_type: u32, data: &[u8]) -> Result<()> {
+    writer.write_u32::<LittleEndian>(sec_type)?;
+    writer.write_u64::<LittleEndian>(data.len() as u64)?;
+    writer.write_all(data)
+}
+
+fn header_section(header: &Header) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    data.write_u32::<LittleEndian>(header.field_size)?;
+    data.write_all(&header.prime_size)?;
+    data.write_u32::<LittleEndian>(header.n_wires)?;
+    data.write_u32::<LittleEndian>(header.n_pub_out)?;
+    data.write_u32::<LittleEndian>(header.n_pub_in)?;
+    data.write_u32::<LittleEndian>(header.n_prv_in)?;
+    data.write_u64::<LittleEndian>(header.n_labels)?;
+    data.write_u32::<LittleEndian>(header.n_constraints)?;
+    Ok(data)
+}
+
+/// Creates a bn256 header for a circuit with the given sizes
+pub fn bn256_header(n_wires: u32, n_pub_out: u32, n_pub_in: u32, n_prv_in: u32, n_labels: u64, n_constraints: u32) -> Header {
+    Header {
+        field_size: 32,
+        prime_size: hex!("010000f093f5e1439170b97948e833285d588181b64550b829a031e1724e6430").to_vec(),
+        n_wires,
+        n_pub_out,
+        n_pub_in,
+        n_prv_in,
+        n_labels,
+        n_constraints,
+    }
+}
+
+/// Writes circom r1cs file, version 1 with header, constraints and wire to label map sections
+pub fn write<W: Write>(mut writer: W, file: &R1CSFile<Bn256>) -> Result<()> {
+    writer.write_all(&[0x72, 0x31, 0x63, 0x73])?; // magic = "r1cs"
+    writer.write_u32::<LittleEndian>(file.version)?;
+    writer.write_u32::<LittleEndian>(3)?;
+
+    write_section(&mut writer, 1, &header_section(&file.header)?)?;
+
+    let mut constraints = vec![];
+    for (a, b, c) in file.constraints.iter() {
+        write_constraint_vec::<_, Bn256>(&mut constraints, a)?;
+        write_constraint_vec::<_, Bn256>(&mut constraints, b)?;
+        write_constraint_vec::<_, Bn256>(&mut constraints, c)?;
+    }
+    write_section(&mut writer, 2, &constraints)?;
+
+    let mut map = vec![];
+    for label in file.wire_mapping.iter() {
+        map.write_u64::<LittleEndian>(*label)?;
+    }
+    write_section(&mut writer, 3, &map)
+}
+
+#[test]
+fn roundtrip() {
+    use std::io::Cursor;
+    use crate::circom_circuit::sample_circuit;
+
+    let r1cs = sample_circuit(0).r1cs;
+    let file = R1CSFile {
+        version: 1,
+        header: bn256_header(3, 1, 0, 1, 5, 1),
+        constraints: r1cs.constraints.clone(),
+        wire_mapping: vec![0, 1, 4],
+    };
+    let mut data = vec![];
+    write(&mut data, &file).unwrap();
+
+    let loaded = crate::r1cs_reader::read(Cursor::new(data)).unwrap();
+    assert_eq!(loaded.header.n_wires, 3);
+    assert_eq!(loaded.header.n_prv_in, 1);
+    assert_eq!(loaded.header.n_labels, 5);
+    assert_eq!(loaded.constraints, r1cs.constraints);
+    assert_eq!(loaded.wire_mapping, vec![0, 1, 4]);
+}
@@ -0,0 +1,295 @@
+//! Captures any bellman `Circuit` into zkutil's `R1CS`, so that circuits written
+//! in Rust can go through the same setup/prove/export pipeline as circom ones
+//! and be saved as .r1cs/.wtns/.sym files for circom tooling.
+//!
+//! ```ignore
+//! let recorded = record_circuit(MyCircuit { x: Some(x) }, true)?;
+//! recorded.write_files("circuit.r1cs", Some("witness.wtns"), Some("circuit.sym"))?;
+//! ```
+extern crate bellman_ce;
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::collections::HashMap;
+use bellman_ce::{
+    Circuit,
+    ConstraintSystem,
+    Index,
+    LinearCombination,
+    SynthesisError,
+    Variable,
+    pairing::{
+        Engine,
+        ff::Field,
+        bn256::Bn256,
+    },
+};
+
+use crate::circom_circuit::{CircomCircuit, Constraint, R1CS};
+use crate::r1cs_reader::R1CSFile;
+use crate::r1cs_writer::bn256_header;
+use crate::sym_reader::SymEntry;
+
+type RecordedLc<E> = Vec<(Index, <E as bellman_ce::pairing::ff::ScalarEngine>::Fr)>;
+
+/// Variable allocated during synthesis
+struct Allocation<E: Engine> {
+    name: String,
+    component: usize,
+    value: Option<E::Fr>,
+}
+
+/// `ConstraintSystem` that records constraints and, optionally, variable assignments
+pub struct RecordingSystem<E: Engine> {
+    with_witness: bool,
+    inputs: Vec<Allocation<E>>,
+    aux: Vec<Allocation<E>>,
+    constraints: Vec<(RecordedLc<E>, RecordedLc<E>, RecordedLc<E>)>,
+    namespace: Vec<String>,
+    /// Namespace path -> component index for sym entries
+    components: HashMap<String, usize>,
+}
+
+/// Circuit synthesized by `RecordingSystem`
+pub struct RecordedCircuit<E: Engine> {
+    pub r1cs: R1CS<E>,
+    pub witness: Option<Vec<E::Fr>>,
+    /// Namespaced name for every wire, e.g. `main.hasher.out`
+    pub names: Vec<String>,
+    /// Component index for every wire, one per namespace
+    pub components: Vec<usize>,
+}
+
+impl<E: Engine> RecordingSystem<E> {
+    /// Variable values are only computed if `with_witness` is set
+    pub fn new(with_witness: bool) -> Self {
+        let mut cs = RecordingSystem {
+            with_witness,
+            inputs: vec![],
+            aux: vec![],
+            constraints: vec![],
+            namespace: vec!["main".to_string()],
+            components: HashMap::new(),
+        };
+        let component = cs.component();
+        cs.inputs.push(Allocation { name: "one".to_string(), component, value: Some(E::Fr::one()) });
+        cs
+    }
+
+    fn component(&mut self) -> usize {
+        let next = self.components.len();
+        *self.components.entry(self.namespace.join(".")).or_insert(next)
+    }
+
+    fn allocation<F>(&mut self, name: String, f: F) -> Result<Allocation<E>, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>
+    {
+        let value = if self.with_witness { Some(f()?) } else { None };
+        let mut path = self.namespace.clone();
+        path.push(name);
+        Ok(Allocation { name: path.join("."), component: self.component(), value })
+    }
+
+    fn record(lc: LinearCombination<E>) -> RecordedLc<E> {
+        lc.as_ref().iter().map(|(var, coeff)| (var.get_unchecked(), *coeff)).collect()
+    }
+
+    pub fn finish(self) -> RecordedCircuit<E> {
+        let num_inputs = self.inputs.len();
+        let map = |lc: RecordedLc<E>| lc.into_iter()
+            .map(|(index, coeff)| match index {
+                Index::Input(i) => (i, coeff),
+                Index::Aux(i) => (num_inputs + i, coeff),
+            })
+            .collect();
+        let constraints: Vec<Constraint<E>> = self.constraints.into_iter()
+            .map(|(a, b, c)| (map(a), map(b), map(c)))
+            .collect();
+        let allocations = self.inputs.into_iter().chain(self.aux).collect::<Vec<_>>();
+        let witness = if self.with_witness {
+            Some(allocations.iter().map(|a| a.value.unwrap()).collect())
+        } else {
+            None
+        };
+        RecordedCircuit {
+            r1cs: R1CS {
+                num_inputs,
+                num_aux: allocations.len() - num_inputs,
+                num_variables: allocations.len(),
+                constraints,
+            },
+            witness,
+            names: allocations.iter().map(|a| a.name.clone()).collect(),
+            components: allocations.iter().map(|a| a.component).collect(),
+        }
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for RecordingSystem<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let allocation = self.allocation(annotation().into(), f)?;
+        self.aux.push(allocation);
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let allocation = self.allocation(annotation().into(), f)?;
+        self.inputs.push(allocation);
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _annotation: A, a: LA, b: LB, c: LC)
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        self.constraints.push((
+            Self::record(a(LinearCombination::zero())),
+            Self::record(b(LinearCombination::zero())),
+            Self::record(c(LinearCombination::zero())),
+        ));
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        self.namespace.pop();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Synthesizes the circuit, computing the witness if `with_witness` is set
+pub fn record_circuit<E: Engine, C: Circuit<E>>(circuit: C, with_witness: bool) -> Result<RecordedCircuit<E>, SynthesisError> {
+    let mut cs = RecordingSystem::new(with_witness);
+    circuit.synthesize(&mut cs)?;
+    Ok(cs.finish())
+}
+
+impl<E: Engine> RecordedCircuit<E> {
+    pub fn circom_circuit(&self) -> CircomCircuit<E> {
+        CircomCircuit {
+            r1cs: self.r1cs.clone(),
+            witness: self.witness.clone(),
+            wire_mapping: None,
+        }
+    }
+
+    /// One sym entry per wire, labels are the same as wire indices
+    pub fn sym_entries(&self) -> Vec<SymEntry> {
+        self.names.iter().zip(self.components.iter()).enumerate()
+            .map(|(i, (name, component))| SymEntry {
+                label: i as u64,
+                wire: Some(i),
+                component: *component as u64,
+                name: name.clone(),
+            })
+            .collect()
+    }
+}
+
+impl RecordedCircuit<Bn256> {
+    /// All public wires are written as public outputs
+    pub fn r1cs_file(&self) -> R1CSFile<Bn256> {
+        let r1cs = &self.r1cs;
+        R1CSFile {
+            version: 1,
+            header: bn256_header(
+                r1cs.num_variables as u32,
+                r1cs.num_inputs as u32 - 1,
+                0,
+                0,
+                r1cs.num_variables as u64,
+                r1cs.constraints.len() as u32,
+            ),
+            constraints: r1cs.constraints.clone(),
+            wire_mapping: (0..r1cs.num_variables as u64).collect(),
+        }
+    }
+
+    /// Writes .r1cs and optionally .wtns and .sym files
+    pub fn write_files(&self, r1cs: &str, wtns: Option<&str>, sym: Option<&str>) -> io::Result<()> {
+        crate::r1cs_writer::write(BufWriter::new(File::create(r1cs)?), &self.r1cs_file())?;
+        if let Some(filename) = wtns {
+            let witness = self.witness.as_ref()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Circuit was recorded without witness"))?;
+            crate::wtns_writer::write(BufWriter::new(File::create(filename)?), witness)?;
+        }
+        if let Some(filename) = sym {
+            crate::sym_writer::write(BufWriter::new(File::create(filename)?), &self.sym_entries())?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn record_and_prove() {
+    use bellman_ce::pairing::{ff::PrimeField, bn256::Fr};
+
+    // out = x^3 + x + 5
+    struct Cube {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Bn256> for Cube {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x_val = self.x;
+            let x = cs.alloc(|| "x", || x_val.ok_or(SynthesisError::AssignmentMissing))?;
+            let mut cs = cs.namespace(|| "cube");
+            let sq_val = x_val.map(|mut x| { x.square(); x });
+            let sq = cs.alloc(|| "sq", || sq_val.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "sq", |lc| lc + x, |lc| lc + x, |lc| lc + sq);
+            let out_val = x_val.map(|x| {
+                let mut out = sq_val.unwrap();
+                out.mul_assign(&x);
+                out.add_assign(&x);
+                out.add_assign(&Fr::from_str("5").unwrap());
+                out
+            });
+            let out = cs.alloc_input(|| "out", || out_val.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "out", |lc| lc + sq, |lc| lc + x, |lc| lc + out - x - (Fr::from_str("5").unwrap(), CS::one()));
+            Ok(())
+        }
+    }
+
+    let setup = record_circuit(Cube { x: None }, false).unwrap();
+    assert!(setup.witness.is_none());
+    let recorded = record_circuit(Cube { x: Some(Fr::from_str("3").unwrap()) }, true).unwrap();
+    assert_eq!(recorded.names, vec!["one", "main.cube.out", "main.x", "main.cube.sq"]);
+    assert_eq!(recorded.components, vec![0, 1, 0, 1]);
+    assert_eq!(recorded.r1cs.hash(), setup.r1cs.hash());
+    let witness = recorded.witness.as_ref().unwrap();
+    assert_eq!(witness[1], Fr::from_str("35").unwrap());
+    assert_eq!(recorded.r1cs.first_unsatisfied(witness), None);
+
+    let filename = std::env::temp_dir().join("zkutil_recorded.r1cs").to_string_lossy().to_string();
+    let error = setup.write_files(&filename, Some(&filename.replace(".r1cs", ".wtns")), None).err().unwrap();
+    assert_eq!(error.to_string(), "Circuit was recorded without witness");
+    std::fs::remove_file(&filename).unwrap();
+
+    // a recorded circuit proves with the params of the original one
+    let mut fixture = crate::fixture::fixture(3);
+    let recorded = record_circuit(fixture.circuit.clone(), true).unwrap();
+    assert_eq!(recorded.r1cs.hash(), fixture.circuit.r1cs.hash());
+    let circuit = recorded.circom_circuit();
+    let inputs = circuit.get_public_inputs().unwrap();
+    fixture.prove_and_verify(circuit, &inputs);
+
+    let mut data = vec![];
+    crate::r1cs_writer::write(&mut data, &recorded.r1cs_file()).unwrap();
+    let loaded = crate::circom_circuit::r1cs_from_bin(std::io::Cursor::new(data)).unwrap().0;
+    assert_eq!(loaded.hash(), recorded.r1cs.hash());
+}
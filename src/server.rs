@@ -0,0 +1,326 @@
+//! A small HTTP/JSON server that keeps params and R1CS in memory
+//! and generates proofs on request.
+//!
+//! Endpoints:
+//! * `POST /prove` with `{"witness": [...]}`, returns `{"proof": {...}, "public": [...]}`.
+//!   Instead of the witness, `{"input": {...}}` keyed by wire index or signal name can be sent
+//!   for circuits that `solver` can calculate the witness for
+//! * `POST /verify` with `{"proof": {...}, "public": [...]}`, returns `{"valid": true|false}`
+//! * `GET /health` and `GET /metrics`
+//!
+//! Request bodies are read by the worker that handles the request, so a slow
+//! client doesn't hold up the accept loop.
+extern crate bellman_ce;
+extern crate tiny_http;
+
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use bellman_ce::pairing::{
+    ff::PrimeField,
+    bn256::{Bn256, Fr},
+};
+
+use crate::circom_circuit::{
+    CircomCircuitRef,
+    R1CS,
+    proof_to_json,
+    create_rng,
+    parse_proof_with_inputs_json,
+};
+use crate::optimizer::try_remap_witness;
+use crate::prover::Prover;
+use crate::solver::{inputs_from_json, solve};
+use crate::utils::synthesis_error_message;
+use crate::verifier::Verifier;
+
+pub struct ServerOptions {
+    /// Number of proofs generated in parallel
+    pub workers: usize,
+    /// Max number of proving jobs waiting for a worker, requests above that are rejected
+    pub queue_size: usize,
+    /// Signal names for `input` requests, indexed by wire
+    pub names: Vec<Option<String>>,
+    /// Requests with a larger body are rejected
+    pub max_body_size: u64,
+    /// Wire map from `optimize`, `witness` requests are then for the original circuit
+    pub wire_mapping: Option<Vec<usize>>,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        ServerOptions {
+            workers: 1,
+            queue_size: 16,
+            names: vec![],
+            max_body_size: 64 * 1024 * 1024,
+            wire_mapping: None,
+        }
+    }
+}
+
+struct State {
+    prover: Prover<Bn256>,
+    verifier: Verifier<Bn256>,
+    r1cs: R1CS<Bn256>,
+    names: Vec<Option<String>>,
+    max_body_size: u64,
+    wire_mapping: Option<Vec<usize>>,
+    queue_size: usize,
+    queued: AtomicUsize,
+    proofs: AtomicUsize,
+    proofs_failed: AtomicUsize,
+    proving_time_ms: AtomicU64,
+    verifications: AtomicUsize,
+    rejected: AtomicUsize,
+    started: Instant,
+}
+
+pub struct ProvingServer {
+    http: Server,
+    state: Arc<State>,
+    /// Proving requests, handled by `workers` threads
+    jobs: SyncSender<Request>,
+    /// Verification requests, handled by a separate thread so they don't wait for proofs
+    verifications: SyncSender<Request>,
+}
+
+impl ProvingServer {
+    pub fn bind(addr: &str, prover: Prover<Bn256>, r1cs: R1CS<Bn256>, options: ServerOptions) -> io::Result<ProvingServer> {
+        prover.check_circuit(&r1cs)?;
+        let http = Server::http(addr).map_err(|e| io::Error::other(e.to_string()))?;
+        let state = Arc::new(State {
+            verifier: prover.verifier(),
+            prover,
+            r1cs,
+            names: options.names,
+            max_body_size: options.max_body_size,
+            wire_mapping: options.wire_mapping,
+            queue_size: options.queue_size,
+            queued: AtomicUsize::new(0),
+            proofs: AtomicUsize::new(0),
+            proofs_failed: AtomicUsize::new(0),
+            proving_time_ms: AtomicU64::new(0),
+            verifications: AtomicUsize::new(0),
+            rejected: AtomicUsize::new(0),
+            started: Instant::now(),
+        });
+        let (jobs, receiver) = sync_channel(options.queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..options.workers.max(1) {
+            let state = state.clone();
+            let receiver = receiver.clone();
+            thread::spawn(move || run_worker(&state, &receiver));
+        }
+        let (verifications, receiver) = sync_channel(options.queue_size);
+        {
+            let state = state.clone();
+            thread::spawn(move || run_verifier(&state, &receiver));
+        }
+        Ok(ProvingServer { http, state, jobs, verifications })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Handles requests until `shutdown` is called
+    pub fn run(&self) {
+        while let Ok(request) = self.http.recv() {
+            self.handle(request);
+        }
+    }
+
+    pub fn shutdown(&self) {
+        self.http.unblock();
+    }
+
+    fn handle(&self, request: Request) {
+        let state = &self.state;
+        match (request.method(), request.url()) {
+            (Method::Get, "/health") => respond(request, 200, json!({ "status": "ok" })),
+            (Method::Get, "/metrics") => respond(request, 200, metrics(state)),
+            (Method::Post, "/verify") => {
+                if let Err(TrySendError::Full(request)) | Err(TrySendError::Disconnected(request)) = self.verifications.try_send(request) {
+                    state.rejected.fetch_add(1, Ordering::SeqCst);
+                    respond(request, 503, json!({ "error": "Verification queue is full" }));
+                }
+            }
+            (Method::Post, "/prove") => {
+                state.queued.fetch_add(1, Ordering::SeqCst);
+                if let Err(TrySendError::Full(request)) | Err(TrySendError::Disconnected(request)) = self.jobs.try_send(request) {
+                    state.queued.fetch_sub(1, Ordering::SeqCst);
+                    state.rejected.fetch_add(1, Ordering::SeqCst);
+                    respond(request, 503, json!({ "error": "Proving queue is full" }));
+                }
+            }
+            _ => respond(request, 404, json!({ "error": "Not found" })),
+        }
+    }
+}
+
+fn run_worker(state: &State, receiver: &Mutex<Receiver<Request>>) {
+    loop {
+        let mut request = match receiver.lock().unwrap().recv() {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+        state.queued.fetch_sub(1, Ordering::SeqCst);
+        let witness = match read_body(&mut request, state.max_body_size).and_then(|body| parse_prove_request(&body, &state.r1cs, &state.names, state.wire_mapping.as_deref())) {
+            Ok(witness) => witness,
+            Err(e) => {
+                respond(request, 400, json!({ "error": e.to_string() }));
+                continue;
+            }
+        };
+        let started = Instant::now();
+        let circuit = CircomCircuitRef {
+            r1cs: &state.r1cs,
+            witness: Some(&witness),
+            wire_mapping: None,
+        };
+        // a panic deep in bellman shouldn't take the worker down
+        let result = panic::catch_unwind(AssertUnwindSafe(|| state.prover.prove(circuit, create_rng())))
+            .map_err(|_| "Prover panicked".to_string())
+            .and_then(|proof| proof.map_err(|e| synthesis_error_message(&e)))
+            .and_then(|proof| proof_to_json(&proof).map_err(|e| e.to_string()));
+        state.proofs.fetch_add(1, Ordering::SeqCst);
+        state.proving_time_ms.fetch_add(started.elapsed().as_millis() as u64, Ordering::SeqCst);
+        match result {
+            Ok(proof) => {
+                let proof: Value = serde_json::from_str(&proof).unwrap();
+                let public: Value = serde_json::from_str(&circuit.get_public_inputs_json()).unwrap();
+                respond(request, 200, json!({ "proof": proof, "public": public }));
+            }
+            Err(e) => {
+                state.proofs_failed.fetch_add(1, Ordering::SeqCst);
+                respond(request, 500, json!({ "error": e }));
+            }
+        }
+    }
+}
+
+fn run_verifier(state: &State, receiver: &Receiver<Request>) {
+    while let Ok(mut request) = receiver.recv() {
+        let result = read_body(&mut request, state.max_body_size)
+            .and_then(|body| parse_proof_with_inputs_json(&body))
+            .map_err(|e| e.to_string())
+            .and_then(|(proof, inputs)| state.verifier.verify(&proof, &inputs).map_err(|e| synthesis_error_message(&e)));
+        state.verifications.fetch_add(1, Ordering::SeqCst);
+        match result {
+            Ok(valid) => respond(request, 200, json!({ "valid": valid })),
+            Err(e) => respond(request, 400, json!({ "error": e })),
+        }
+    }
+}
+
+fn parse_prove_request(body: &str, r1cs: &R1CS<Bn256>, names: &[Option<String>], wire_mapping: Option<&[usize]>) -> io::Result<Vec<Fr>> {
+    let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let request: Value = serde_json::from_str(body)?;
+    if let Some(input) = request.get("input") {
+        let inputs = inputs_from_json::<Bn256>(input, names)?;
+        return solve(r1cs, &inputs).map_err(|e| invalid(&e.to_string()));
+    }
+    let witness = request.get("witness")
+        .and_then(|w| w.as_array())
+        .ok_or_else(|| invalid("Expected {\"witness\": [...]}"))?
+        .iter()
+        .map(|x| x.as_str().and_then(Fr::from_str).ok_or_else(|| invalid(&format!("Invalid field element {}", x))))
+        .collect::<io::Result<Vec<_>>>()?;
+    let witness = match wire_mapping {
+        Some(wire_mapping) => try_remap_witness(&witness, wire_mapping)?,
+        None => witness,
+    };
+    if witness.len() != r1cs.num_variables {
+        return Err(invalid(&format!("Witness has {} values, circuit has {} wires", witness.len(), r1cs.num_variables)));
+    }
+    Ok(witness)
+}
+
+fn read_body(request: &mut Request, max_size: u64) -> io::Result<String> {
+    let mut body = String::new();
+    request.as_reader().take(max_size + 1).read_to_string(&mut body)?;
+    if body.len() as u64 > max_size {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Request body is larger than {} bytes", max_size)));
+    }
+    Ok(body)
+}
+
+fn metrics(state: &State) -> Value {
+    let proofs = state.proofs.load(Ordering::SeqCst);
+    let proving_time_ms = state.proving_time_ms.load(Ordering::SeqCst);
+    json!({
+        "uptime_s": state.started.elapsed().as_secs(),
+        "proofs_total": proofs,
+        "proofs_failed": state.proofs_failed.load(Ordering::SeqCst),
+        "proving_time_avg_ms": if proofs == 0 { 0 } else { proving_time_ms / proofs as u64 },
+        "verifications_total": state.verifications.load(Ordering::SeqCst),
+        "queue_length": state.queued.load(Ordering::SeqCst),
+        "queue_capacity": state.queue_size,
+        "rejected_total": state.rejected.load(Ordering::SeqCst),
+        "constraints": state.r1cs.constraints.len(),
+        "public_inputs": state.r1cs.num_inputs - 1,
+    })
+}
+
+fn respond(request: Request, status: u16, body: Value) {
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    // the client might have already disconnected, nothing to do about it
+    let _ = request.respond(response);
+}
+
+#[test]
+fn localhost() {
+    use std::io::Write;
+    use std::net::TcpStream;
+    let fixture = crate::fixture::fixture(0);
+    let options = ServerOptions { max_body_size: 4096, ..ServerOptions::default() };
+    let server = Arc::new(ProvingServer::bind("127.0.0.1:0", fixture.prover(), fixture.circuit.r1cs, options).unwrap());
+    let addr = server.local_addr().unwrap();
+    let handle = {
+        let server = server.clone();
+        thread::spawn(move || server.run())
+    };
+
+    let request = |method: &str, path: &str, body: &str| -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}", method, path, body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    };
+
+    assert_eq!(request("GET", "/health", ""), (200, json!({ "status": "ok" })));
+    let (status, proved) = request("POST", "/prove", r#"{"witness": ["1", "9", "3"]}"#);
+    assert_eq!(status, 200);
+    assert_eq!(proved["public"], json!(["9"]));
+    let (status, verified) = request("POST", "/verify", &proved.to_string());
+    assert_eq!((status, verified), (200, json!({ "valid": true })));
+    let forged = json!({ "proof": proved["proof"], "public": ["16"] });
+    assert_eq!(request("POST", "/verify", &forged.to_string()).1, json!({ "valid": false }));
+    assert_eq!(request("POST", "/prove", r#"{"witness": ["1"]}"#).0, 400);
+    let (status, proved) = request("POST", "/prove", r#"{"input": {"2": "4"}}"#);
+    assert_eq!((status, &proved["public"]), (200, &json!(["16"])));
+    assert_eq!(request("POST", "/prove", r#"{"input": {"1": "4"}}"#).0, 400);
+    let (status, error) = request("POST", "/prove", &format!(r#"{{"witness": ["1", "9", "3"], "padding": "{}"}}"#, "x".repeat(4096)));
+    assert_eq!((status, error), (400, json!({ "error": "Request body is larger than 4096 bytes" })));
+    let (_, metrics) = request("GET", "/metrics", "");
+    assert_eq!(metrics["proofs_total"], json!(2));
+    assert_eq!(metrics["verifications_total"], json!(2));
+
+    server.shutdown();
+    handle.join().unwrap();
+}
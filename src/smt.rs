@@ -0,0 +1,194 @@
+//! SMT-LIB export of R1CS for checking circuit properties with SMT solvers.
+//!
+//! Every wire `i` becomes a constant `w{i}`, wire 0 is replaced by 1. In
+//! uniqueness mode the private wires get a second copy `w{i}_2` constrained by
+//! the same constraints, and the query asks for the copies to differ: `sat`
+//! means there is an alternative witness for the same public inputs.
+extern crate bellman_ce;
+
+use std::io::{self, Write};
+use bellman_ce::pairing::{
+    Engine,
+    ff::{Field, PrimeField},
+};
+
+use crate::circom_circuit::R1CS;
+use crate::utils::repr_to_big;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtEncoding {
+    /// Finite field theory `QF_FF`, supported by cvc5
+    FiniteField,
+    /// Integers reduced modulo the field prime, `QF_NIA`
+    ModularInteger,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtOptions {
+    pub encoding: SmtEncoding,
+    /// Wires shared by both witness copies in uniqueness mode, all public inputs if None
+    pub public: Option<Vec<usize>>,
+    /// Add a second witness copy and ask whether it can differ from the first one
+    pub uniqueness: bool,
+}
+
+impl Default for SmtOptions {
+    fn default() -> Self {
+        SmtOptions { encoding: SmtEncoding::FiniteField, public: None, uniqueness: false }
+    }
+}
+
+struct SmtWriter<'a, E: Engine> {
+    r1cs: &'a R1CS<E>,
+    encoding: SmtEncoding,
+    prime: String,
+    public: Vec<bool>,
+}
+
+impl<'a, E: Engine> SmtWriter<'a, E> {
+    fn constant(&self, value: &str) -> String {
+        match self.encoding {
+            SmtEncoding::FiniteField => format!("(as ff{} F)", value),
+            SmtEncoding::ModularInteger => value.to_string(),
+        }
+    }
+
+    fn wire(&self, index: usize, copy: usize) -> String {
+        if index == 0 {
+            self.constant("1")
+        } else if copy == 1 || self.public[index] {
+            format!("w{}", index)
+        } else {
+            format!("w{}_{}", index, copy)
+        }
+    }
+
+    fn op(&self, name: &str, args: Vec<String>) -> String {
+        match args.len() {
+            0 => self.constant("0"),
+            1 => args.into_iter().next().unwrap(),
+            _ => {
+                let name = match self.encoding {
+                    SmtEncoding::FiniteField => format!("ff.{}", name),
+                    SmtEncoding::ModularInteger => if name == "add" { "+" } else { "*" }.to_string(),
+                };
+                format!("({} {})", name, args.join(" "))
+            }
+        }
+    }
+
+    fn lc(&self, lc: &[(usize, E::Fr)], copy: usize) -> String {
+        let terms = lc.iter()
+            .filter(|(_, coeff)| !coeff.is_zero())
+            .map(|(index, coeff)| if *coeff == E::Fr::one() {
+                self.wire(*index, copy)
+            } else {
+                self.op("mul", vec![self.constant(&repr_to_big(coeff.into_repr())), self.wire(*index, copy)])
+            })
+            .collect();
+        self.op("add", terms)
+    }
+
+    fn declare<W: Write>(&self, mut writer: W, index: usize, copy: usize) -> io::Result<()> {
+        let name = self.wire(index, copy);
+        match self.encoding {
+            SmtEncoding::FiniteField => writeln!(writer, "(declare-fun {} () F)", name),
+            SmtEncoding::ModularInteger => {
+                writeln!(writer, "(declare-fun {} () Int)", name)?;
+                writeln!(writer, "(assert (and (<= 0 {}) (< {} {})))", name, name, self.prime)
+            }
+        }
+    }
+
+    fn constraints<W: Write>(&self, mut writer: W, copy: usize) -> io::Result<()> {
+        for (i, (a, b, c)) in self.r1cs.constraints.iter().enumerate() {
+            let ab = self.op("mul", vec![self.lc(a, copy), self.lc(b, copy)]);
+            let c = self.lc(c, copy);
+            match self.encoding {
+                SmtEncoding::FiniteField => writeln!(writer, "(assert (= {} {})) ; constraint {}", ab, c, i)?,
+                SmtEncoding::ModularInteger => writeln!(writer, "(assert (= (mod (- {} {}) {}) 0)) ; constraint {}", ab, c, self.prime, i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes SMT-LIB 2 script with the circuit constraints
+pub fn write_smt<W: Write, E: Engine>(mut writer: W, r1cs: &R1CS<E>, options: &SmtOptions) -> io::Result<()> {
+    let mut public = vec![false; r1cs.num_variables];
+    match &options.public {
+        Some(wires) => for wire in wires.iter() {
+            if *wire >= r1cs.num_variables {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Wire {} is out of range", wire)));
+            }
+            public[*wire] = true;
+        },
+        None => for flag in public.iter_mut().take(r1cs.num_inputs) {
+            *flag = true;
+        },
+    }
+    let w = SmtWriter {
+        r1cs,
+        encoding: options.encoding,
+        prime: repr_to_big(E::Fr::char()),
+        public,
+    };
+
+    let public_wires = (1..r1cs.num_variables).filter(|i| w.public[*i]).map(|i| format!("w{}", i)).collect::<Vec<_>>();
+    writeln!(writer, "; {} wires, {} constraints", r1cs.num_variables, r1cs.constraints.len())?;
+    writeln!(writer, "; public wires: {}", public_wires.join(" "))?;
+    match options.encoding {
+        SmtEncoding::FiniteField => {
+            writeln!(writer, "(set-logic QF_FF)")?;
+            writeln!(writer, "(define-sort F () (_ FiniteField {}))", w.prime)?;
+        }
+        SmtEncoding::ModularInteger => writeln!(writer, "(set-logic QF_NIA)")?,
+    }
+    for index in 1..r1cs.num_variables {
+        w.declare(&mut writer, index, 1)?;
+    }
+    w.constraints(&mut writer, 1)?;
+
+    if options.uniqueness {
+        writeln!(writer, "; second witness copy with the same public wires")?;
+        let private = (1..r1cs.num_variables).filter(|i| !w.public[*i]).collect::<Vec<_>>();
+        for index in private.iter() {
+            w.declare(&mut writer, *index, 2)?;
+        }
+        w.constraints(&mut writer, 2)?;
+        let distinct = private.iter()
+            .map(|i| format!("(not (= {} {}))", w.wire(*i, 1), w.wire(*i, 2)))
+            .collect::<Vec<_>>();
+        match distinct.len() {
+            0 => writeln!(writer, "(assert false)")?,
+            1 => writeln!(writer, "(assert {})", distinct[0])?,
+            _ => writeln!(writer, "(assert (or {}))", distinct.join(" "))?,
+        }
+    }
+    writeln!(writer, "(check-sat)")
+}
+
+#[test]
+fn smt_encodings() {
+    use bellman_ce::pairing::bn256::Bn256;
+    use crate::circom_circuit::sample_circuit;
+
+    let r1cs = sample_circuit(0).r1cs;
+    let mut data = vec![];
+    write_smt(&mut data, &r1cs, &SmtOptions::default()).unwrap();
+    let text = String::from_utf8(data).unwrap();
+    assert!(text.contains("(define-sort F () (_ FiniteField 21888242871839275222246405745257275088548364400416034343698204186575808495617))"));
+    assert!(text.contains("(assert (= (ff.mul w2 w2) w1)) ; constraint 0"));
+    assert!(!text.contains("w2_2"));
+
+    let options = SmtOptions { encoding: SmtEncoding::ModularInteger, public: None, uniqueness: true };
+    let mut data = vec![];
+    write_smt(&mut data, &r1cs, &options).unwrap();
+    let text = String::from_utf8(data).unwrap();
+    assert!(text.contains("(set-logic QF_NIA)"));
+    assert!(text.contains("(assert (= (mod (- (* w2_2 w2_2) w1) 21888242871839275222246405745257275088548364400416034343698204186575808495617) 0)) ; constraint 0"));
+    assert!(text.contains("(assert (not (= w2 w2_2)))"));
+
+    let options = SmtOptions { public: Some(vec![5]), ..SmtOptions::default() };
+    assert!(write_smt::<_, Bn256>(vec![], &r1cs, &options).is_err());
+}
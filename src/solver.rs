@@ -0,0 +1,207 @@
+//! Native witness calculation for straight-line circuits: starting from the
+//! inputs, constraints with exactly one unknown wire that appears linearly are
+//! solved for it until nothing changes.
+extern crate bellman_ce;
+
+use std::fmt;
+use std::io;
+use std::collections::{HashMap, VecDeque};
+use serde_json::Value;
+use bellman_ce::pairing::{
+    Engine,
+    ff::{Field, PrimeField},
+};
+
+use crate::circom_circuit::{Constraint, R1CS, eval_lc, is_satisfied};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolveError {
+    /// Wires that couldn't be derived from the inputs
+    Unsolved(Vec<usize>),
+    /// Index of a constraint that doesn't hold for the inputs
+    Unsatisfied(usize),
+    /// Input wire that is out of range, and the number of wires in the circuit
+    UnknownWire(usize, usize),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::Unsolved(wires) => write!(f, "{} wires can't be derived from the inputs", wires.len()),
+            SolveError::Unsatisfied(i) => write!(f, "Inputs don't satisfy constraint {}", i),
+            SolveError::UnknownWire(wire, wires) => write!(f, "Input wire {} is out of range, circuit has {} wires", wire, wires),
+        }
+    }
+}
+
+fn coeff_of<E: Engine>(lc: &[(usize, E::Fr)], wire: usize) -> E::Fr {
+    let mut result = E::Fr::zero();
+    for (index, coeff) in lc.iter() {
+        if *index == wire {
+            result.add_assign(coeff);
+        }
+    }
+    result
+}
+
+/// Value of the wire that satisfies the constraint, if the constraint is linear in it
+pub(crate) fn solve_for<E: Engine>(constraint: &Constraint<E>, witness: &[E::Fr], wire: usize) -> Option<E::Fr> {
+    let (a_coeff, b_coeff) = (coeff_of::<E>(&constraint.0, wire), coeff_of::<E>(&constraint.1, wire));
+    if !a_coeff.is_zero() && !b_coeff.is_zero() {
+        return None;
+    }
+    let a = eval_lc::<E>(&constraint.0, witness);
+    let b = eval_lc::<E>(&constraint.1, witness);
+    // A * B - C changes by k for every unit of the wire
+    let mut k = coeff_of::<E>(&constraint.2, wire);
+    k.negate();
+    let mut term = a_coeff;
+    term.mul_assign(&b);
+    k.add_assign(&term);
+    let mut term = b_coeff;
+    term.mul_assign(&a);
+    k.add_assign(&term);
+
+    let mut error = a;
+    error.mul_assign(&b);
+    error.sub_assign(&eval_lc::<E>(&constraint.2, witness));
+    error.mul_assign(&k.inverse()?);
+    let mut value = witness[wire];
+    value.sub_assign(&error);
+    Some(value)
+}
+
+/// Derives the full witness from the given wire values
+pub fn solve<E: Engine>(r1cs: &R1CS<E>, inputs: &HashMap<usize, E::Fr>) -> Result<Vec<E::Fr>, SolveError> {
+    let mut witness = vec![E::Fr::zero(); r1cs.num_variables];
+    let mut known = vec![false; r1cs.num_variables];
+    witness[0] = E::Fr::one();
+    known[0] = true;
+    for (wire, value) in inputs.iter() {
+        if *wire >= r1cs.num_variables {
+            return Err(SolveError::UnknownWire(*wire, r1cs.num_variables));
+        }
+        witness[*wire] = *value;
+        known[*wire] = true;
+    }
+
+    let mut users = vec![vec![]; r1cs.num_variables];
+    let mut unknown = vec![0usize; r1cs.constraints.len()];
+    for (i, (a, b, c)) in r1cs.constraints.iter().enumerate() {
+        let mut wires = a.iter().chain(b.iter()).chain(c.iter()).map(|(index, _)| *index).collect::<Vec<_>>();
+        wires.sort_unstable();
+        wires.dedup();
+        for wire in wires {
+            users[wire].push(i);
+            if !known[wire] {
+                unknown[i] += 1;
+            }
+        }
+    }
+
+    let mut queue = (0..r1cs.constraints.len()).filter(|i| unknown[*i] <= 1).collect::<VecDeque<_>>();
+    while let Some(i) = queue.pop_front() {
+        let constraint = &r1cs.constraints[i];
+        if unknown[i] == 0 {
+            if !is_satisfied::<E>(constraint, &witness) {
+                return Err(SolveError::Unsatisfied(i));
+            }
+            continue;
+        }
+        let wire = constraint.0.iter().chain(constraint.1.iter()).chain(constraint.2.iter())
+            .map(|(index, _)| *index)
+            .find(|index| !known[*index])
+            .unwrap();
+        let value = match solve_for::<E>(constraint, &witness, wire) {
+            Some(value) => value,
+            // quadratic in the wire, might still be checked once the wire is known
+            None => continue,
+        };
+        witness[wire] = value;
+        known[wire] = true;
+        for user in users[wire].iter() {
+            unknown[*user] -= 1;
+            if unknown[*user] <= 1 {
+                queue.push_back(*user);
+            }
+        }
+    }
+
+    let unsolved = (0..r1cs.num_variables).filter(|i| !known[*i]).collect::<Vec<_>>();
+    if !unsolved.is_empty() {
+        return Err(SolveError::Unsolved(unsolved));
+    }
+    match r1cs.first_unsatisfied(&witness) {
+        Some(i) => Err(SolveError::Unsatisfied(i)),
+        None => Ok(witness),
+    }
+}
+
+fn flatten_input(key: String, value: &Value, result: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Array(values) => for (i, v) in values.iter().enumerate() {
+            flatten_input(format!("{}[{}]", key, i), v, result);
+        },
+        _ => result.push((key, value.clone())),
+    }
+}
+
+/// Parses inputs JSON object keyed by wire index or signal name, circom input.json
+/// names without `main.` prefix and nested arrays are also accepted
+pub fn inputs_from_json<E: Engine>(inputs: &Value, names: &[Option<String>]) -> io::Result<HashMap<usize, E::Fr>> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let object = inputs.as_object().ok_or_else(|| invalid("Expected inputs JSON object".to_string()))?;
+    let mut flat = vec![];
+    for (key, value) in object.iter() {
+        flatten_input(key.clone(), value, &mut flat);
+    }
+    // the first wire wins if a name repeats
+    let indices = names.iter().enumerate().rev()
+        .filter_map(|(i, name)| name.as_deref().map(|name| (name, i)))
+        .collect::<HashMap<_, _>>();
+    let mut result = HashMap::new();
+    for (key, value) in flat {
+        let wire = key.parse::<usize>().ok()
+            .or_else(|| indices.get(key.as_str()).cloned())
+            .or_else(|| indices.get(format!("main.{}", key).as_str()).cloned())
+            .ok_or_else(|| invalid(format!("Unknown signal {}", key)))?;
+        let value = match &value {
+            Value::String(s) => E::Fr::from_str(s),
+            Value::Number(n) => E::Fr::from_str(&n.to_string()),
+            _ => None,
+        }.ok_or_else(|| invalid(format!("Invalid field element {} for {}", value, key)))?;
+        result.insert(wire, value);
+    }
+    Ok(result)
+}
+
+#[test]
+fn solve_straight_line() {
+    use bellman_ce::pairing::bn256::{Bn256, Fr};
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+    let mut minus_five = fr("5");
+    minus_five.negate();
+
+    // wires: 1, out, x, sq; sq = x * x, out = sq * x + 5
+    let r1cs = R1CS::<Bn256> {
+        num_inputs: 2,
+        num_aux: 2,
+        num_variables: 4,
+        constraints: vec![
+            (vec![(2, fr("1"))], vec![(2, fr("1"))], vec![(3, fr("1"))]),
+            (vec![(3, fr("1"))], vec![(2, fr("1"))], vec![(1, fr("1")), (0, minus_five)]),
+        ],
+    };
+    let names = vec![None, Some("main.out".to_string()), Some("main.x".to_string()), Some("main.sq".to_string())];
+    let inputs = inputs_from_json::<Bn256>(&serde_json::json!({ "x": 3 }), &names).unwrap();
+    assert_eq!(solve(&r1cs, &inputs).unwrap(), vec![fr("1"), fr("32"), fr("3"), fr("9")]);
+
+    let inputs = inputs_from_json::<Bn256>(&serde_json::json!({ "main.x": "3", "1": "33" }), &names).unwrap();
+    assert_eq!(solve(&r1cs, &inputs), Err(SolveError::Unsatisfied(1)));
+    // x can't be derived from out, x * x is quadratic
+    let inputs = inputs_from_json::<Bn256>(&serde_json::json!({ "out": "32" }), &names).unwrap();
+    assert_eq!(solve(&r1cs, &inputs), Err(SolveError::Unsolved(vec![2, 3])));
+    assert!(inputs_from_json::<Bn256>(&serde_json::json!({ "y": "1" }), &names).is_err());
+    let inputs = inputs_from_json::<Bn256>(&serde_json::json!({ "x": "3", "4": "1" }), &names).unwrap();
+    assert_eq!(solve(&r1cs, &inputs), Err(SolveError::UnknownWire(4, 4)));
+}
@@ -0,0 +1,70 @@
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Result, ErrorKind, Error};
+
+/// Line of circom .sym file: `labelIdx,wireIdx,componentIdx,name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymEntry {
+    pub label: u64,
+    /// None if the signal was optimized away by circom
+    pub wire: Option<usize>,
+    pub component: u64,
+    pub name: String,
+}
+
+fn parse_line(line: &str) -> Option<SymEntry> {
+    let mut parts = line.splitn(4, ',');
+    let label = parts.next()?.trim().parse().ok()?;
+    let wire: i64 = parts.next()?.trim().parse().ok()?;
+    let component = parts.next()?.trim().parse().ok()?;
+    let name = parts.next()?.trim().to_string();
+    Some(SymEntry {
+        label,
+        wire: if wire < 0 { None } else { Some(wire as usize) },
+        component,
+        name,
+    })
+}
+
+pub fn read<R: BufRead>(reader: R) -> Result<Vec<SymEntry>> {
+    let mut result = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = parse_line(&line)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Invalid sym entry on line {}", i + 1)))?;
+        result.push(entry);
+    }
+    Ok(result)
+}
+
+pub fn read_file(filename: &str) -> Result<Vec<SymEntry>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    read(BufReader::new(reader))
+}
+
+/// First signal name for every wire, wires without names are None
+pub fn wire_names(entries: &[SymEntry], num_wires: usize) -> Vec<Option<String>> {
+    let mut names = vec![None; num_wires];
+    for entry in entries.iter() {
+        if let Some(wire) = entry.wire {
+            if wire < num_wires && names[wire].is_none() {
+                names[wire] = Some(entry.name.clone());
+            }
+        }
+    }
+    names
+}
+
+#[test]
+fn parse_sym() {
+    let data = "1,1,0,main.out\n2,2,0,main.x\n3,-1,1,main.sq.tmp\n4,1,1,main.sq.out\n";
+    let entries = read(data.as_bytes()).unwrap();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[2], SymEntry { label: 3, wire: None, component: 1, name: "main.sq.tmp".to_string() });
+    assert_eq!(wire_names(&entries, 3), vec![None, Some("main.out".to_string()), Some("main.x".to_string())]);
+    assert!(read("1,x,0,main.out".as_bytes()).is_err());
+}
@@ -0,0 +1,11 @@
+use std::io::{Result, Write};
+use crate::sym_reader::SymEntry;
+
+/// Writes circom .sym file, one `labelIdx,wireIdx,componentIdx,name` line per entry
+pub fn write<W: Write>(mut writer: W, entries: &[SymEntry]) -> Result<()> {
+    for entry in entries.iter() {
+        let wire = entry.wire.map_or(-1, |w| w as i64);
+        writeln!(writer, "{},{},{},{}", entry.label, wire, entry.component, entry.name)?;
+    }
+    Ok(())
+}
@@ -9,6 +9,7 @@ use itertools::Itertools;
 use num_bigint::BigUint;
 use num_traits::Num;
 use bellman_ce::{
+    SynthesisError,
     groth16::Proof,
     pairing::{
         ff::PrimeField,
@@ -22,6 +23,19 @@ use bellman_ce::{
     },
 };
 
+/// Error message for `SynthesisError`, its `Display` impl recurses forever for anything but I/O errors
+#[allow(deprecated)]
+pub fn synthesis_error_message(e: &SynthesisError) -> String {
+    match e {
+        SynthesisError::IoError(e) => e.to_string(),
+        e => std::error::Error::description(e).to_string(),
+    }
+}
+
+pub fn bytes_to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 pub fn repr_to_big<T: Display>(r: T) -> String {
     BigUint::from_str_radix(&format!("{}", r)[2..], 16).unwrap().to_str_radix(10)
 }
@@ -0,0 +1,232 @@
+extern crate bellman_ce;
+extern crate rand;
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian};
+use itertools::Itertools;
+use rand::{Rng, OsRng};
+use bellman_ce::{
+    SynthesisError,
+    groth16::{
+        Proof,
+        VerifyingKey,
+    },
+    pairing::{
+        Engine,
+        CurveAffine,
+        CurveProjective,
+        ff::{Field, PrimeField, PrimeFieldRepr},
+        bn256::{Bn256, Fq, Fq2, Fq6, Fq12},
+    },
+};
+
+use crate::circom_circuit::ProofWithInputs;
+
+const PREPARED_VK_MAGIC: [u8; 4] = [0x7a, 0x6b, 0x76, 0x6b]; // magic = "zkvk"
+const PREPARED_VK_VERSION: u32 = 1;
+
+/// Verifying key with all the values that don't depend on a proof precomputed,
+/// meant to be kept around for verifying many proofs.
+#[derive(Clone)]
+pub struct Verifier<E: Engine> {
+    pub vk: VerifyingKey<E>,
+    alpha_g1_beta_g2: E::Fqk,
+    neg_gamma_g2: <E::G2Affine as CurveAffine>::Prepared,
+    neg_delta_g2: <E::G2Affine as CurveAffine>::Prepared,
+}
+
+impl<E: Engine> Verifier<E> {
+    pub fn new(vk: &VerifyingKey<E>) -> Verifier<E> {
+        let alpha_g1_beta_g2 = E::pairing(vk.alpha_g1, vk.beta_g2);
+        Self::with_pairing(vk.clone(), alpha_g1_beta_g2)
+    }
+
+    fn with_pairing(vk: VerifyingKey<E>, alpha_g1_beta_g2: E::Fqk) -> Verifier<E> {
+        let mut neg_gamma = vk.gamma_g2;
+        neg_gamma.negate();
+        let mut neg_delta = vk.delta_g2;
+        neg_delta.negate();
+        Verifier {
+            alpha_g1_beta_g2,
+            neg_gamma_g2: neg_gamma.prepare(),
+            neg_delta_g2: neg_delta.prepare(),
+            vk,
+        }
+    }
+
+    pub fn verify(&self, proof: &Proof<E>, inputs: &[E::Fr]) -> Result<bool, SynthesisError> {
+        if inputs.len() + 1 != self.vk.ic.len() {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+        let mut acc = self.vk.ic[0].into_projective();
+        for (input, base) in inputs.iter().zip(self.vk.ic.iter().skip(1)) {
+            acc.add_assign(&base.mul(input.into_repr()));
+        }
+
+        // A * B + inputs * (-gamma) + C * (-delta) = alpha * beta
+        Ok(E::final_exponentiation(&E::miller_loop([
+            (&proof.a.prepare(), &proof.b.prepare()),
+            (&acc.into_affine().prepare(), &self.neg_gamma_g2),
+            (&proof.c.prepare(), &self.neg_delta_g2),
+        ].iter())).unwrap() == self.alpha_g1_beta_g2)
+    }
+
+    /// Verifies many proofs at once.
+    ///
+    /// Every proof is scaled by a random factor and all verification equations are
+    /// folded into a single Miller loop with one final exponentiation. Returns
+    /// `Ok(false)` if at least one proof is invalid, use `find_invalid_proofs` to
+    /// find out which ones.
+    pub fn verify_batch(&self, proofs: &[ProofWithInputs<E>]) -> Result<bool, SynthesisError> {
+        // randomness must not be predictable by whoever created the proofs
+        let mut rng = OsRng::new().map_err(SynthesisError::from)?;
+        if proofs.iter().any(|(_, inputs)| inputs.len() + 1 != self.vk.ic.len()) {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+        if proofs.is_empty() {
+            return Ok(true);
+        }
+
+        // For random r_i the combined equation is:
+        // prod e(r_i * A_i, B_i) * e(sum r_i * acc_i, -gamma) * e(sum r_i * C_i, -delta) = (alpha * beta) ^ sum r_i
+        // where acc_i = IC_0 + sum x_ij * IC_j
+        let mut r_sum = E::Fr::zero();
+        let mut ic_coeffs = vec![E::Fr::zero(); self.vk.ic.len()];
+        let mut c_acc = E::G1::zero();
+        let mut a_scaled = Vec::with_capacity(proofs.len());
+        for (proof, inputs) in proofs {
+            let r: E::Fr = rng.gen();
+            r_sum.add_assign(&r);
+            for (coeff, input) in ic_coeffs.iter_mut().skip(1).zip(inputs.iter()) {
+                let mut term = *input;
+                term.mul_assign(&r);
+                coeff.add_assign(&term);
+            }
+            c_acc.add_assign(&proof.c.mul(r.into_repr()));
+            a_scaled.push(proof.a.mul(r.into_repr()).into_affine().prepare());
+        }
+        ic_coeffs[0] = r_sum;
+
+        let mut ic_acc = E::G1::zero();
+        for (base, coeff) in self.vk.ic.iter().zip(ic_coeffs.iter()) {
+            ic_acc.add_assign(&base.mul(coeff.into_repr()));
+        }
+
+        let b_prepared = proofs.iter().map(|(proof, _)| proof.b.prepare()).collect_vec();
+        let ic_prepared = ic_acc.into_affine().prepare();
+        let c_prepared = c_acc.into_affine().prepare();
+        let terms = a_scaled.iter().zip(b_prepared.iter())
+            .chain(vec![(&ic_prepared, &self.neg_gamma_g2), (&c_prepared, &self.neg_delta_g2)])
+            .collect_vec();
+
+        let result = E::final_exponentiation(&E::miller_loop(terms.iter())).unwrap();
+        Ok(result == self.alpha_g1_beta_g2.pow(r_sum.into_repr()))
+    }
+
+    /// Returns indexes of the invalid proofs in a batch, bisecting the batch
+    /// whenever the combined check fails.
+    pub fn find_invalid_proofs(&self, proofs: &[ProofWithInputs<E>]) -> Result<Vec<usize>, SynthesisError> {
+        let mut invalid = vec![];
+        self.find_invalid_proofs_rec(proofs, 0, &mut invalid)?;
+        Ok(invalid)
+    }
+
+    fn find_invalid_proofs_rec(&self, proofs: &[ProofWithInputs<E>], offset: usize, invalid: &mut Vec<usize>) -> Result<(), SynthesisError> {
+        if self.verify_batch(proofs)? {
+            return Ok(());
+        }
+        if proofs.len() == 1 {
+            invalid.push(offset);
+            return Ok(());
+        }
+        let mid = proofs.len() / 2;
+        self.find_invalid_proofs_rec(&proofs[..mid], offset, invalid)?;
+        self.find_invalid_proofs_rec(&proofs[mid..], offset + mid, invalid)
+    }
+}
+
+/// Prepared verifying key file format:
+/// magic "zkvk", version, verifying key as written by bellman and e(alpha, beta)
+impl Verifier<Bn256> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&PREPARED_VK_MAGIC)?;
+        writer.write_u32::<LittleEndian>(PREPARED_VK_VERSION)?;
+        self.vk.write(&mut writer)?;
+        for fq in fq12_to_vec(&self.alpha_g1_beta_g2) {
+            fq.into_repr().write_le(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Verifier<Bn256>> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != PREPARED_VK_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid magic number"));
+        }
+        if reader.read_u32::<LittleEndian>()? != PREPARED_VK_VERSION {
+            return Err(Error::new(ErrorKind::InvalidData, "Unsupported version"));
+        }
+        let vk = VerifyingKey::<Bn256>::read(&mut reader)?;
+        let mut coeffs = Vec::with_capacity(12);
+        for _ in 0..12 {
+            let mut repr = Fq::zero().into_repr();
+            repr.read_le(&mut reader)?;
+            coeffs.push(Fq::from_repr(repr).map_err(|e| Error::new(ErrorKind::InvalidData, e))?);
+        }
+        Ok(Self::with_pairing(vk, fq12_from_vec(&coeffs)))
+    }
+}
+
+fn fq12_to_vec(p: &Fq12) -> Vec<Fq> {
+    [p.c0.c0, p.c0.c1, p.c0.c2, p.c1.c0, p.c1.c1, p.c1.c2]
+        .iter()
+        .flat_map(|fq2| vec![fq2.c0, fq2.c1])
+        .collect_vec()
+}
+
+fn fq12_from_vec(v: &[Fq]) -> Fq12 {
+    let fq2 = |i: usize| Fq2 { c0: v[i * 2], c1: v[i * 2 + 1] };
+    Fq12 {
+        c0: Fq6 { c0: fq2(0), c1: fq2(1), c2: fq2(2) },
+        c1: Fq6 { c0: fq2(3), c1: fq2(4), c2: fq2(5) },
+    }
+}
+
+pub fn load_verifier_file(filename: &str) -> io::Result<Verifier<Bn256>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)?;
+    Verifier::read(BufReader::new(reader))
+}
+
+pub fn save_verifier_file(verifier: &Verifier<Bn256>, filename: &str) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(filename)?);
+    verifier.write(writer)
+}
+
+#[test]
+fn prepared_vk_roundtrip() {
+    let mut fixture = crate::fixture::fixture(3);
+    let inputs = fixture.circuit.get_public_inputs().unwrap();
+    let proof = fixture.prove_and_verify(fixture.circuit.clone(), &inputs);
+
+    let prover = fixture.prover();
+    let mut data = vec![];
+    prover.verifier().write(&mut data).unwrap();
+    let verifier = Verifier::<Bn256>::read(&data[..]).unwrap();
+    assert!(verifier.verify(&proof, &inputs).unwrap());
+    assert!(verifier.verify_batch(&[(proof.clone(), inputs.clone())]).unwrap());
+    assert!(!verifier.verify(&proof, &crate::circom_circuit::sample_circuit(4).get_public_inputs().unwrap()).unwrap());
+    assert!(verifier.verify(&proof, &[]).is_err());
+
+    let error = |data: &[u8]| Verifier::<Bn256>::read(data).err().unwrap().kind();
+    assert_eq!(error(&data[..data.len() - 1]), ErrorKind::UnexpectedEof);
+    let mut corrupted = data.clone();
+    corrupted[0] = b'x';
+    assert_eq!(error(&corrupted), ErrorKind::InvalidData);
+    let mut corrupted = data.clone();
+    corrupted[4] = 2;
+    assert_eq!(error(&corrupted), ErrorKind::InvalidData);
+}
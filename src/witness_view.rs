@@ -0,0 +1,119 @@
+//! Witness inspection with signal names from circom .sym files
+extern crate bellman_ce;
+
+use bellman_ce::pairing::{
+    Engine,
+    ff::PrimeField,
+};
+
+use crate::sym_reader::SymEntry;
+use crate::utils::{repr_to_big, repr_to_hex};
+
+/// Witness value with its signal name
+pub struct WitnessEntry<E: Engine> {
+    /// Index in the witness
+    pub index: usize,
+    pub name: Option<String>,
+    pub value: E::Fr,
+}
+
+/// Matches names like `main.hasher.*`, `*` matches any sequence and `?` any single character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    // position after the last `*` and the name position it was matched at
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+/// Witness index of a sym entry. Witnesses of older circom versions contain all
+/// signals and are indexed by label, newer ones only contain wires.
+fn entry_index(entry: &SymEntry, by_label: bool) -> Option<usize> {
+    if by_label { Some(entry.label as usize) } else { entry.wire }
+}
+
+/// First signal name for every witness index
+pub fn index_names(entries: &[SymEntry], by_label: bool, len: usize) -> Vec<Option<String>> {
+    let mut names = vec![None; len];
+    for entry in entries.iter() {
+        if let Some(index) = entry_index(entry, by_label).filter(|i| *i < len) {
+            if names[index].is_none() {
+                names[index] = Some(entry.name.clone());
+            }
+        }
+    }
+    names
+}
+
+/// Witness values matching the filter, one per sym entry if sym is given, otherwise one per index.
+/// Without sym the filter is matched against the index, e.g. `1?`
+pub fn witness_entries<E: Engine>(witness: &[E::Fr], sym: Option<&[SymEntry]>, by_label: bool, filter: Option<&str>) -> Vec<WitnessEntry<E>> {
+    let matches = |name: &str| filter.is_none_or(|pattern| glob_match(pattern, name));
+    match sym {
+        Some(entries) => entries.iter()
+            .filter(|entry| matches(&entry.name))
+            .filter_map(|entry| entry_index(entry, by_label).filter(|i| *i < witness.len()).map(|index| WitnessEntry {
+                index,
+                name: Some(entry.name.clone()),
+                value: witness[index],
+            }))
+            .collect(),
+        None => witness.iter().enumerate()
+            .filter(|(index, _)| matches(&index.to_string()))
+            .map(|(index, value)| WitnessEntry { index, name: None, value: *value })
+            .collect(),
+    }
+}
+
+/// Indices where the witnesses differ, including the extra values of a longer one
+pub fn diff_witnesses<E: Engine>(a: &[E::Fr], b: &[E::Fr]) -> Vec<usize> {
+    (0..a.len().max(b.len()))
+        .filter(|i| a.get(*i) != b.get(*i))
+        .collect()
+}
+
+pub fn format_value<E: Engine>(value: &E::Fr, hex: bool) -> String {
+    if hex {
+        format!("0x{}", repr_to_hex(value.into_repr()))
+    } else {
+        repr_to_big(value.into_repr())
+    }
+}
+
+#[test]
+fn glob_and_entries() {
+    use bellman_ce::pairing::bn256::{Bn256, Fr};
+    use crate::sym_reader::read;
+
+    assert!(glob_match("main.hasher.*", "main.hasher.out"));
+    assert!(glob_match("main.*.out", "main.hasher.inner.out"));
+    assert!(glob_match("main.in[?]", "main.in[3]"));
+    assert!(!glob_match("main.hasher.*", "main.out"));
+    assert!(!glob_match("main.*.out", "main.hasher.outs"));
+
+    let sym = read("1,1,0,main.out\n2,-1,0,main.tmp\n3,2,1,main.hasher.in\n4,2,1,main.hasher.out\n".as_bytes()).unwrap();
+    let fr = |v: &str| Fr::from_str(v).unwrap();
+    let witness = vec![fr("1"), fr("10"), fr("20")];
+    let entries = witness_entries::<Bn256>(&witness, Some(&sym), false, Some("main.hasher.*"));
+    assert_eq!(entries.iter().map(|e| (e.index, e.name.clone().unwrap())).collect::<Vec<_>>(),
+        vec![(2, "main.hasher.in".to_string()), (2, "main.hasher.out".to_string())]);
+    assert_eq!(index_names(&sym, true, 5)[2], Some("main.tmp".to_string()));
+    assert_eq!(format_value::<Bn256>(&witness[1], true), "0x000000000000000000000000000000000000000000000000000000000000000a");
+
+    assert_eq!(diff_witnesses::<Bn256>(&witness, &[fr("1"), fr("11"), fr("20"), fr("0")]), vec![1, 3]);
+}
@@ -0,0 +1,40 @@
+use byteorder::{WriteBytesExt, LittleEndian};
+use std::io::{Result, Write};
+use bellman_ce::pairing::{
+    bn256::Fr,
+    ff::{
+        PrimeField, PrimeFieldRepr,
+    }
+};
+
+/// Writes circom wtns file, version 2, for bn256 witness
+pub fn write<W: Write>(mut writer: W, witness: &[Fr]) -> Result<()> {
+    writer.write_all(&[119, 116, 110, 115])?; // magic = "wtns"
+    writer.write_u32::<LittleEndian>(2)?;
+    writer.write_u32::<LittleEndian>(2)?;
+
+    writer.write_u32::<LittleEndian>(1)?;
+    writer.write_u64::<LittleEndian>(4 + 32 + 4)?;
+    writer.write_u32::<LittleEndian>(32)?;
+    writer.write_all(&hex!("010000f093f5e1439170b97948e833285d588181b64550b829a031e1724e6430"))?;
+    writer.write_u32::<LittleEndian>(witness.len() as u32)?;
+
+    writer.write_u32::<LittleEndian>(2)?;
+    writer.write_u64::<LittleEndian>(witness.len() as u64 * 32)?;
+    for value in witness.iter() {
+        value.into_repr().write_le(&mut writer)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn roundtrip() {
+    use bellman_ce::pairing::bn256::Bn256;
+    use crate::circom_circuit::sample_circuit;
+
+    let witness = sample_circuit(3).witness.unwrap();
+    let mut data = vec![];
+    write(&mut data, &witness).unwrap();
+    let loaded = crate::wtns_reader::read::<Bn256, _>(&data[..]).unwrap();
+    assert_eq!(loaded.witness, witness);
+}